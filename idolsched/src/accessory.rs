@@ -11,6 +11,7 @@ pub struct Acc {
     pub appeal: f64,
     pub stamina: f64,
     pub technique: f64,
+    pub info: AccInfo, // what the account said, so teams can refer to it
 }
 
 impl Acc {
@@ -21,6 +22,7 @@ impl Acc {
             kind: info.kind,
             skill: Skill::default(),
             appeal, stamina, technique,
+            info: *info,
         }
     }
 
@@ -36,6 +38,23 @@ impl Acc {
     }
 }
 
+// the highest level an accessory can reach at its limit break:
+// thirty short of the top of its stat table at LB0, five more per limit break,
+// and the top of the table at LB5 (so a UR goes 30, 35, 40, 45, 50, 60)
+pub fn max_level(info: &AccInfo) -> u8 {
+    let top = match (info.kind, info.rarity) {
+        (Empty, _) => return info.lv,
+        (Bangle, _) | (Belt, _) | (Choker, _) => tables::DLP_STATS.len(),
+        (_, Rarity::R) => tables::CMN_STATS_R.len(),
+        (_, Rarity::Sr) => tables::CMN_STATS_S.len(),
+        (_, Rarity::Ur) => tables::CMN_STATS_U.len(),
+    } as u8;
+    match info.lb {
+        0 ..= 4 => top - 30 + 5 * info.lb,
+        _ => top,
+    }
+}
+
 fn stats(info: &AccInfo) -> (f64, f64, f64) {
     let lv_i = info.lv.max(5) as usize - 1;
 
@@ -270,3 +289,22 @@ mod tables {
     ];
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    // every accessory in the sample account is levelled as far as its limit break allows
+    #[test]
+    fn max_levels_match_the_account() {
+        let acct = testing::acct();
+        assert!(acct.accs.iter().any(|info| info.lb == 0) && acct.accs.iter().any(|info| info.lb == 5));
+        for info in acct.accs.iter() {
+            assert_eq!(max_level(info), info.lv, "{:?}", info);
+        }
+        let sr = AccInfo { rarity: Rarity::Sr, lb: 2, ..acct.accs[0] };
+        assert_eq!(max_level(&AccInfo { kind: Brooch, ..sr }), 30);
+        assert_eq!(max_level(&AccInfo { kind: Brooch, lb: 5, ..sr }), 50);
+    }
+}
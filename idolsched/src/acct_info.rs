@@ -25,7 +25,7 @@ impl AcctInfo {
         ];
         for &ordinal in r1_ordinals {
             if let None = self.album.get(&ordinal) {
-                self.album.insert(ordinal, CardInfo { lb: 0, idolized: false });
+                self.album.insert(ordinal, CardInfo { lb: 0, idolized: false, fatigue: 0 });
             }
         }
     }
@@ -64,6 +64,8 @@ impl Default for BondInfo {
 pub struct CardInfo {
     pub lb: u8,
     pub idolized: bool,
+    #[serde(default)]
+    pub fatigue: u8, // percent of appeal lost on tower floors, from floors already played
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
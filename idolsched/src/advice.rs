@@ -0,0 +1,144 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use sifas_data::prelude::*;
+use card_consumer::json_card::TrimCard;
+use super::PlayGlob;
+use super::card::Card;
+use super::accessory::{self, Acc};
+use super::acct_info::AcctInfo;
+use super::schedule::Schedule;
+
+// something a player could spend resources on
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Upgrade {
+    CardLb(u32), // card ordinal; one more limit break
+    Feed(u32), // card ordinal; fill in the skill tree
+    AccLevel(usize, u8), // inventory index; level it up to this
+}
+
+// what an upgrade costs, in units that are worth comparing with each other
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Resource {
+    Lb(Rarity), // limit break items (or copies of the card)
+    Training(Rarity), // skill tree materials for a card
+    AccLevels, // accessory experience, per level
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Advice {
+    pub upgrade: Upgrade,
+    pub resource: Resource,
+    pub amount: u32, // units of `resource`
+    pub gain: f64, // voltage, after re-optimizing the team around the upgrade
+    pub team: Schedule, // the re-optimized team
+}
+
+impl Advice {
+    pub fn gain_per_unit(&self) -> f64 {
+        self.gain / self.amount.max(1) as f64
+    }
+}
+
+// every upgrade the account could make, each tried out by hill climbing from `team`
+// (see local_search::hill_climb and PlayGlob::for_climbing) with the upgrade in place.
+// the team itself is hill climbed first, so that gains aren't just improvements
+// that were available all along.
+// returns the upgrades that gain anything, grouped by resource,
+// then by gain per unit of that resource, best first.
+pub fn advise(glob: &PlayGlob, card_details: &BTreeMap<u32, TrimCard>, acct: &AcctInfo, team: &Schedule)
+-> Vec<Advice> {
+    let glob = &glob.for_climbing();
+    let (base, base_energy, _) = local_search::hill_climb(*team, glob);
+    let mut advice = Vec::new();
+    let mut try_upgrade = |upgrade: Upgrade, resource: Resource, amount: u32, upgraded: PlayGlob| {
+        let (team, energy, _) = local_search::hill_climb(base, &upgraded);
+        let gain = base_energy - energy;
+        if gain > 0.0 {
+            advice.push(Advice { upgrade, resource, amount, gain, team });
+        }
+    };
+
+    for (card_i, card) in glob.album.iter().enumerate() {
+        let (jc, info) = match (card_details.get(&card.ordinal), acct.album.get(&card.ordinal)) {
+            (Some(jc), Some(info)) => (jc, info),
+            _ => continue, // one of the starter Rs padding out a small album
+        };
+        if info.lb < 5 {
+            let upgraded = with_card(glob, card_i, jc, info.lb + 1, info.idolized);
+            try_upgrade(Upgrade::CardLb(card.ordinal), Resource::Lb(card.rarity), 1, upgraded);
+        }
+        if !info.idolized {
+            let upgraded = with_card(glob, card_i, jc, info.lb, true);
+            try_upgrade(Upgrade::Feed(card.ordinal), Resource::Training(card.rarity), 1, upgraded);
+        }
+    }
+
+    for (acc_i, acc) in glob.inventory.iter().enumerate() {
+        let max = accessory::max_level(&acc.info);
+        if acc.info.lv < max {
+            let mut info = acc.info;
+            info.lv = max;
+            let mut upgraded = glob.clone();
+            upgraded.inventory[acc_i] = Acc::from_info(&info);
+            let levels = (max - acc.info.lv) as u32;
+            try_upgrade(Upgrade::AccLevel(acc_i, max), Resource::AccLevels, levels, upgraded);
+        }
+    }
+
+    advice.sort_by(|a, b| {
+        resource_order(a.resource).cmp(&resource_order(b.resource))
+            .then(b.gain_per_unit().partial_cmp(&a.gain_per_unit()).unwrap())
+    });
+    advice
+}
+
+// limit breaks, then training, then accessories; rarest first
+fn resource_order(resource: Resource) -> (u8, Reverse<Rarity>) {
+    match resource {
+        Resource::Lb(rarity) => (0, Reverse(rarity)),
+        Resource::Training(rarity) => (1, Reverse(rarity)),
+        Resource::AccLevels => (2, Reverse(Rarity::R)),
+    }
+}
+
+fn with_card(glob: &PlayGlob, card_i: usize, jc: &TrimCard, lb: u8, fed: bool) -> PlayGlob {
+    let mut upgraded = glob.clone();
+    let fatigue = glob.album[card_i].fatigue;
+    upgraded.album[card_i] = Card::instantiate_json(jc, lb, fed);
+    upgraded.album[card_i].fatigue = fatigue;
+    upgraded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use local_search::PortableRng;
+    use crate::testing;
+    use crate::acct_info::AccInfo;
+
+    #[test]
+    fn advice_for_the_sample_account() {
+        let mut glob = testing::glob(20);
+        let acct = testing::acct();
+        // every accessory in the account is already at its cap, so knock one back
+        let acc_i = glob.inventory.iter().position(|acc| acc.info.rarity == Rarity::Ur && acc.info.lb == 1).unwrap();
+        glob.inventory[acc_i] = Acc::from_info(&AccInfo { lv: 20, ..glob.inventory[acc_i].info });
+        let team = Schedule::new_random(&mut PortableRng::seed_from_u64(41), &glob);
+        let advice = advise(&glob, testing::cards(), &acct, &team);
+        assert!(!advice.is_empty());
+        for (a, b) in advice.iter().zip(advice.iter().skip(1)) {
+            let (a_order, b_order) = (resource_order(a.resource), resource_order(b.resource));
+            assert!(a_order < b_order || a_order == b_order && a.gain_per_unit() >= b.gain_per_unit());
+        }
+        for a in advice.iter() {
+            assert!(a.gain > 0.0);
+            match a.upgrade {
+                Upgrade::CardLb(ordinal) => assert!(acct.album[&ordinal].lb < 5),
+                Upgrade::Feed(ordinal) => assert!(!acct.album[&ordinal].idolized),
+                // only as far as LB1 allows, and only the accessory that isn't there yet
+                Upgrade::AccLevel(i, lv) => assert_eq!((i, lv, a.amount), (acc_i, 35, 15)),
+            }
+        }
+    }
+}
@@ -18,6 +18,7 @@ pub struct Card {
     pub skill_mask: u64,
     pub tap_skill: Skill,
     pub etc_skills: Vec<Skill>,
+    pub fatigue: f64, // fraction of appeal lost on tower floors
 }
 
 impl Card {
@@ -60,6 +61,7 @@ impl Card {
             skill_mask,
             tap_skill,
             etc_skills,
+            fatigue: 0.0,
             level, appeal, stamina, technique,
         }
     }
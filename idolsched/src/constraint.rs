@@ -0,0 +1,507 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Formatter, Display};
+use serde::{Deserialize, Serialize};
+use rand::Rng;
+use local_search::portable::{self, UniformIndex};
+use super::PlayGlob;
+use super::schedule::Schedule;
+use super::card::Card;
+use super::skill::group_mask;
+use super::team::AccRef;
+use super::acct_info::AccInfo;
+
+use ConstraintError::*;
+
+// rules that every team the search looks at has to follow, in terms of album and inventory indexes.
+// apart from the empty default, the only way to get one is ConstraintSpec::to_constraints,
+// which makes sure that at least one team follows them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Constraints {
+    locked: [Option<usize>; 9], // GGGBBBRRR
+    required: Vec<usize>, // anywhere on the team; doesn't repeat locked cards
+    banned_cards: Vec<bool>, // by album index; empty if nothing is banned
+    banned_accs: Vec<bool>, // by inventory index; empty if nothing is banned
+    minimums: Vec<(u64, Vec<bool>, usize)>, // group mask, which album cards are in it, and how many the team needs
+}
+
+impl Constraints {
+    pub fn is_empty(&self) -> bool {
+        self.locked.iter().all(Option::is_none)
+            && self.required.is_empty()
+            && self.banned_cards.is_empty()
+            && self.banned_accs.is_empty()
+            && self.minimums.is_empty()
+    }
+
+    pub fn card_allowed(&self, card_i: usize) -> bool {
+        self.banned_cards.get(card_i) != Some(&true)
+    }
+
+    pub fn acc_allowed(&self, acc_i: usize) -> bool {
+        self.banned_accs.get(acc_i) != Some(&true)
+    }
+
+    pub fn allows(&self, sched: &Schedule) -> bool {
+        self.locked.iter().zip(sched.cards.iter()).all(|(lock, &card_i)| lock.iter().all(|&l| l == card_i))
+            && self.required.iter().all(|card_i| sched.cards.contains(card_i))
+            && sched.cards.iter().all(|&card_i| self.card_allowed(card_i))
+            && sched.accs.iter().all(|&acc_i| self.acc_allowed(acc_i))
+            && self.minimums.iter().all(|(_, members, n)| count(members, &sched.cards) >= *n)
+    }
+
+    // nine different cards that follow the rules, but otherwise random,
+    // and none of them marked in `unavailable` (which can be empty, or shorter than the album).
+    // locked and required cards go in first, then cards for any group that's short
+    // (preferring ones that count for as many short groups as possible), then anything allowed.
+    // with no constraints this picks exactly what Schedule::new_random always has.
+    // None if `unavailable` rules out every such team.
+    pub(crate) fn random_cards<R: Rng + ?Sized>(&self, rng: &mut R, album_size: usize, unavailable: &[bool])
+    -> Option<[usize; 9]> {
+        let mut slots = self.locked;
+        let mut used = vec![false; album_size];
+        used[.. unavailable.len()].copy_from_slice(unavailable);
+        let fixed = self.locked.iter().flatten().chain(self.required.iter());
+        if fixed.clone().any(|&card_i| used[card_i]) {
+            return None;
+        }
+        for &card_i in fixed {
+            used[card_i] = true;
+        }
+        for &card_i in self.required.iter() {
+            place(rng, &mut slots, card_i);
+        }
+
+        loop {
+            let team: Vec<usize> = slots.iter().flatten().copied().collect();
+            let short: Vec<&[bool]> = self.minimums.iter()
+                .filter(|(_, members, n)| count(members, &team) < *n)
+                .map(|(_, members, _)| &members[..])
+                .collect();
+            if short.is_empty() {
+                break;
+            }
+            let helps = |card_i: usize| short.iter().filter(|members| members[card_i]).count();
+            let free: Vec<usize> = (0 .. album_size)
+                .filter(|&card_i| !used[card_i] && self.card_allowed(card_i))
+                .collect();
+            let most = free.iter().map(|&card_i| helps(card_i)).max().unwrap_or(0);
+            if most == 0 {
+                return None;
+            }
+            let picks: Vec<usize> = free.into_iter().filter(|&card_i| helps(card_i) == most).collect();
+            let card_i = *portable::choose(rng, &picks).unwrap();
+            place(rng, &mut slots, card_i);
+            used[card_i] = true;
+        }
+
+        let empty = slots.iter().filter(|slot| slot.is_none()).count();
+        let left = (0 .. album_size).filter(|&card_i| !used[card_i] && self.card_allowed(card_i)).count();
+        if left < empty {
+            return None;
+        }
+        let card_dist = UniformIndex::new(album_size);
+        for slot in slots.iter_mut().filter(|slot| slot.is_none()) {
+            let mut v = rng.sample(card_dist);
+            while used[v] || !self.card_allowed(v) {
+                v = rng.sample(card_dist);
+            }
+            *slot = Some(v);
+            used[v] = true;
+        }
+
+        let mut cards = [0; 9];
+        for (card, slot) in cards.iter_mut().zip(slots.iter()) {
+            *card = slot.unwrap();
+        }
+        Some(cards)
+    }
+
+    // the same rules for the album with `card` added to the end, which they don't mention
+    pub(crate) fn with_card(&self, card: &Card) -> Constraints {
+        let mut constraints = self.clone();
+        if !constraints.banned_cards.is_empty() {
+            constraints.banned_cards.push(false);
+        }
+        for (mask, members, _) in constraints.minimums.iter_mut() {
+            members.push(card.skill_mask & *mask != 0);
+        }
+        constraints
+    }
+}
+
+fn count(members: &[bool], cards: &[usize]) -> usize {
+    cards.iter().filter(|&&card_i| members[card_i]).count()
+}
+
+// puts `card_i` in a random empty slot
+fn place<R: Rng + ?Sized>(rng: &mut R, slots: &mut [Option<usize>; 9], card_i: usize) {
+    let empty: Vec<usize> = (0 .. 9).filter(|&i| slots[i].is_none()).collect();
+    slots[*portable::choose(rng, &empty).unwrap()] = Some(card_i);
+}
+
+// constraints as a person would write them, in the same terms as team::TeamSpec:
+// cards by ordinal, accessories by index into the account's list or by description.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConstraintSpec {
+    pub lock: [Option<u32>; 9], // card ordinals that must be in these positions; GGGBBBRRR
+    pub require: Vec<u32>, // card ordinals that must be somewhere on the team
+    pub ban_cards: Vec<u32>, // card ordinals
+    pub ban_accs: Vec<AccRef>, // an AccRef::Item bans every copy of that accessory
+    pub at_least: BTreeMap<String, usize>, // group name (see skill::group_mask) to number of cards
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConstraintError {
+    CardNotOwned(u32),
+    NoSuchAcc(usize),
+    AccNotOwned(AccInfo),
+    UnknownGroup(String),
+    LockedTwice(u32),
+    BannedButNeeded(u32),
+    GroupTooSmall(String),
+    TooManyCards,
+    TooFewCards,
+    TooFewAccs,
+    NothingToSearch,
+    BadJson(String),
+}
+
+impl Display for ConstraintError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            CardNotOwned(ord) => write!(f, "card {} is not in the album", ord),
+            NoSuchAcc(i) => write!(f, "there is no accessory #{}", i),
+            AccNotOwned(info) => write!(f,
+                "there is no {:?} {:?} {:?} (LB{} Lv{} SL{}) in the accessory list",
+                info.rarity, info.attribute, info.kind, info.lb, info.lv, info.sl
+            ),
+            UnknownGroup(name) => write!(f,
+                "\"{}\" isn't a school, subunit or attribute (try e.g. muse, qu4rtz or smile)", name
+            ),
+            LockedTwice(ord) => write!(f, "card {} is locked into more than one position", ord),
+            BannedButNeeded(ord) => write!(f, "card {} is banned, but also locked or required", ord),
+            GroupTooSmall(name) => write!(f, "there aren't enough {} cards to choose from", name),
+            TooManyCards => write!(f, "the locked, required and at_least cards don't fit on one team"),
+            TooFewCards => write!(f, "fewer than 9 cards are left once the banned ones are taken out"),
+            TooFewAccs => write!(f, "fewer than 9 accessories are left once the banned ones are taken out"),
+            NothingToSearch => write!(f, "the constraints (with the move weights) decide the whole team, so there's nothing to search"),
+            BadJson(e) => write!(f, "couldn't parse constraints json: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ConstraintError {}
+
+impl ConstraintSpec {
+    pub fn parse(text: &str) -> Result<ConstraintSpec, ConstraintError> {
+        serde_json::from_str(text).map_err(|e| BadJson(e.to_string()))
+    }
+
+    // at_least is checked as if no card counted for more than one group,
+    // which can turn down some constraints that could just about be met, but never lets through
+    // ones that can't.
+    pub fn to_constraints(&self, glob: &PlayGlob) -> Result<Constraints, ConstraintError> {
+        let card_index = |ordinal: u32| glob.album.iter()
+            .position(|card| card.ordinal == ordinal)
+            .ok_or(CardNotOwned(ordinal));
+
+        let mut locked = [None; 9];
+        for (slot, &ordinal) in self.lock.iter().enumerate() {
+            if let Some(ordinal) = ordinal {
+                let card_i = card_index(ordinal)?;
+                if locked.contains(&Some(card_i)) {
+                    return Err(LockedTwice(ordinal));
+                }
+                locked[slot] = Some(card_i);
+            }
+        }
+
+        let mut required = Vec::new();
+        for &ordinal in self.require.iter() {
+            let card_i = card_index(ordinal)?;
+            if !locked.contains(&Some(card_i)) && !required.contains(&card_i) {
+                required.push(card_i);
+            }
+        }
+
+        let mut banned_cards = Vec::new();
+        if !self.ban_cards.is_empty() {
+            banned_cards.resize(glob.album.len(), false);
+            for &ordinal in self.ban_cards.iter() {
+                let card_i = card_index(ordinal)?;
+                if locked.contains(&Some(card_i)) || required.contains(&card_i) {
+                    return Err(BannedButNeeded(ordinal));
+                }
+                banned_cards[card_i] = true;
+            }
+            if banned_cards.iter().filter(|&&banned| !banned).count() < 9 {
+                return Err(TooFewCards);
+            }
+        }
+
+        let mut banned_accs = Vec::new();
+        if !self.ban_accs.is_empty() {
+            banned_accs.resize(glob.inventory.len(), false);
+            for acc in self.ban_accs.iter() {
+                match *acc {
+                    AccRef::Index(acc_i) => {
+                        if acc_i >= glob.inventory.len() {
+                            return Err(NoSuchAcc(acc_i));
+                        }
+                        banned_accs[acc_i] = true;
+                    },
+                    AccRef::Item(info) => {
+                        let mut found = false;
+                        for (acc_i, inv_acc) in glob.inventory.iter().enumerate() {
+                            if inv_acc.info == info {
+                                banned_accs[acc_i] = true;
+                                found = true;
+                            }
+                        }
+                        if !found {
+                            return Err(AccNotOwned(info));
+                        }
+                    },
+                }
+            }
+            if banned_accs.iter().filter(|&&banned| !banned).count() < 9 {
+                return Err(TooFewAccs);
+            }
+        }
+
+        let fixed: Vec<usize> = locked.iter().flatten().chain(required.iter()).copied().collect();
+        let mut shortfall = 0;
+        let mut minimums = Vec::new();
+        for (name, &n) in self.at_least.iter() {
+            let mask = group_mask(name).ok_or_else(|| UnknownGroup(name.clone()))?;
+            let members: Vec<bool> = glob.album.iter().map(|card| card.skill_mask & mask != 0).collect();
+            let short = n.saturating_sub(count(&members, &fixed));
+            let available = (0 .. glob.album.len())
+                .filter(|&card_i| members[card_i] && !fixed.contains(&card_i))
+                .filter(|&card_i| banned_cards.get(card_i) != Some(&true))
+                .count();
+            if available < short {
+                return Err(GroupTooSmall(name.clone()));
+            }
+            shortfall += short;
+            minimums.push((mask, members, n));
+        }
+        if fixed.len() + shortfall > 9 {
+            return Err(TooManyCards);
+        }
+        // whether the moves glob.moves makes could change anything (roughly: a swap that
+        // has two open slots to work with might still break a required or at_least)
+        let moves = glob.moves;
+        let open_slots = locked.iter().filter(|card_i| card_i.is_none()).count();
+        let free_cards = (0 .. glob.album.len()).filter(|&card_i| banned_cards.get(card_i) != Some(&true)).count();
+        let free_accs = (0 .. glob.inventory.len()).filter(|&acc_i| banned_accs.get(acc_i) != Some(&true)).count();
+        let card_swaps = [moves.green_swap, moves.back_swap, moves.formation_swap, moves.strat_swap];
+        let cards_can_change = open_slots >= 1 && moves.card > 0 && free_cards > 9
+            || open_slots >= 2 && card_swaps.iter().any(|&weight| weight > 0);
+        let accs_can_change = moves.acc > 0 && free_accs > 9 || moves.acc_swap > 0;
+        if !cards_can_change && !accs_can_change {
+            return Err(NothingToSearch);
+        }
+
+        Ok(Constraints { locked, required, banned_cards, banned_accs, minimums })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use local_search::PortableRng;
+    use crate::testing;
+    use crate::schedule::MoveWeights;
+
+    fn group(glob: &PlayGlob, name: &str) -> Vec<usize> {
+        let mask = group_mask(name).unwrap();
+        (0 .. glob.album.len()).filter(|&card_i| glob.album[card_i].skill_mask & mask != 0).collect()
+    }
+
+    fn ordinals(glob: &PlayGlob, cards: &[usize]) -> Vec<u32> {
+        cards.iter().map(|&card_i| glob.album[card_i].ordinal).collect()
+    }
+
+    // draws a lot of teams and checks that every one is nine different cards that follow the rules
+    fn check_random(glob: &PlayGlob, constraints: &Constraints, unavailable: &[bool]) -> Vec<[usize; 9]> {
+        let mut rng = PortableRng::seed_from_u64(42);
+        let mut accs = [0; 9];
+        for (acc, acc_i) in accs.iter_mut().zip((0 .. glob.inventory.len()).filter(|&acc_i| constraints.acc_allowed(acc_i))) {
+            *acc = acc_i;
+        }
+        (0 .. 200).map(|_| {
+            let cards = constraints.random_cards(&mut rng, glob.album.len(), unavailable).unwrap();
+            for (i, card_i) in cards.iter().enumerate() {
+                assert!(!cards[.. i].contains(card_i), "{:?}", cards);
+                assert_ne!(unavailable.get(*card_i), Some(&true), "{:?}", cards);
+            }
+            assert!(constraints.allows(&Schedule { cards, sp3: [1, 0, 2], accs }), "{:?}", cards);
+            cards
+        }).collect()
+    }
+
+    #[test]
+    fn no_constraints_picks_as_before() {
+        let glob = testing::glob(120);
+        let album_size = glob.album.len();
+        let mut rng = PortableRng::seed_from_u64(42);
+        let mut old_rng = rng.clone();
+        let card_dist = UniformIndex::new(album_size);
+        for _ in 0 .. 50 {
+            let mut old = [0; 9];
+            for i in 0 .. 9 {
+                let mut v = old_rng.sample(card_dist);
+                while old[.. i].contains(&v) {
+                    v = old_rng.sample(card_dist);
+                }
+                old[i] = v;
+            }
+            assert_eq!(Constraints::default().random_cards(&mut rng, album_size, &[]), Some(old));
+        }
+    }
+
+    #[test]
+    fn lock_require_ban_and_at_least() {
+        let glob = testing::glob(120);
+        let aqours = group(&glob, "aqours");
+        let smile = group(&glob, "smile");
+        let both: Vec<usize> = aqours.iter().filter(|card_i| smile.contains(card_i)).copied().collect();
+        assert!(!both.is_empty());
+        let muse = group(&glob, "muse");
+        let mut spec = ConstraintSpec::default();
+        spec.lock[4] = Some(glob.album[muse[0]].ordinal);
+        spec.require = ordinals(&glob, &[muse[1], both[0], muse[0]]); // muse[0] is locked already
+        spec.ban_cards = ordinals(&glob, &muse[2 ..]);
+        spec.at_least.insert("aqours".to_string(), 3);
+        spec.at_least.insert("smile".to_string(), 2);
+        let constraints = spec.to_constraints(&glob).unwrap();
+        assert_eq!(constraints.required, vec![muse[1], both[0]]);
+        for cards in check_random(&glob, &constraints, &[]) {
+            assert_eq!(cards[4], muse[0]);
+            assert_eq!(cards.iter().filter(|card_i| muse.contains(card_i)).count(), 2);
+        }
+    }
+
+    // with only two slots to spare, the short groups have to share them
+    #[test]
+    fn overlapping_groups() {
+        let glob = testing::glob(120);
+        let aqours = group(&glob, "aqours");
+        let smile = group(&glob, "smile");
+        let neither: Vec<usize> = (0 .. glob.album.len())
+            .filter(|card_i| !aqours.contains(card_i) && !smile.contains(card_i))
+            .collect();
+        let mut spec = ConstraintSpec::default();
+        for (slot, &card_i) in neither[.. 7].iter().enumerate() {
+            spec.lock[slot] = Some(glob.album[card_i].ordinal);
+        }
+        spec.at_least.insert("aqours".to_string(), 1);
+        spec.at_least.insert("smile".to_string(), 1);
+        let constraints = spec.to_constraints(&glob).unwrap();
+        check_random(&glob, &constraints, &[]);
+
+        // at_least is checked as if the groups didn't overlap, so this is turned down
+        // even though two aqours cards that are also smile would do
+        assert!(aqours.iter().filter(|card_i| smile.contains(card_i)).count() >= 2);
+        spec.at_least.insert("aqours".to_string(), 2);
+        spec.at_least.insert("smile".to_string(), 2);
+        assert_eq!(spec.to_constraints(&glob), Err(TooManyCards));
+    }
+
+    // locked and required cards that fill the team leave place() exactly enough room
+    #[test]
+    fn full_team() {
+        let glob = testing::glob(120);
+        let mut spec = ConstraintSpec::default();
+        for slot in 0 .. 7 {
+            spec.lock[slot] = Some(glob.album[slot].ordinal);
+        }
+        spec.require = ordinals(&glob, &[20, 30]);
+        let constraints = spec.to_constraints(&glob).unwrap();
+        for cards in check_random(&glob, &constraints, &[]) {
+            assert_eq!(cards[.. 7], [0, 1, 2, 3, 4, 5, 6]);
+            assert!(cards[7 ..] == [20, 30] || cards[7 ..] == [30, 20]);
+        }
+    }
+
+    #[test]
+    fn unavailable_cards() {
+        let glob = testing::glob(120);
+        let mut spec = ConstraintSpec { require: ordinals(&glob, &[100]), .. ConstraintSpec::default() };
+        spec.at_least.insert("muse".to_string(), 3);
+        let constraints = spec.to_constraints(&glob).unwrap();
+        let mut unavailable = vec![true; 50];
+        unavailable[10] = false;
+        check_random(&glob, &constraints, &unavailable);
+
+        // a required card that's unavailable, or not enough of a group left, rule out every team
+        let mut rng = PortableRng::seed_from_u64(42);
+        unavailable.resize(101, false);
+        unavailable[100] = true;
+        assert_eq!(constraints.random_cards(&mut rng, glob.album.len(), &unavailable), None);
+        let muse = group(&glob, "muse");
+        let unavailable: Vec<bool> = (0 .. glob.album.len()).map(|card_i| muse[2 ..].contains(&card_i)).collect();
+        assert_eq!(constraints.random_cards(&mut rng, glob.album.len(), &unavailable), None);
+    }
+
+    #[test]
+    fn errors() {
+        let glob = testing::glob(120);
+        let ordinal = |card_i: usize| glob.album[card_i].ordinal;
+        let check = |edit: &dyn Fn(&mut ConstraintSpec), error: ConstraintError| {
+            let mut spec = ConstraintSpec::default();
+            edit(&mut spec);
+            assert_eq!(spec.to_constraints(&glob), Err(error));
+        };
+        check(&|spec| spec.require = vec![99999], CardNotOwned(99999));
+        check(&|spec| spec.lock = [Some(ordinal(0)), None, None, None, None, None, None, None, Some(ordinal(0))],
+            LockedTwice(ordinal(0)));
+        check(&|spec| {
+            spec.require = vec![ordinal(3)];
+            spec.ban_cards = vec![ordinal(3)];
+        }, BannedButNeeded(ordinal(3)));
+        check(&|spec| { spec.at_least.insert("nope".to_string(), 1); }, UnknownGroup("nope".to_string()));
+        let muse = group(&glob, "muse");
+        check(&|spec| {
+            spec.ban_cards = ordinals(&glob, &muse[2 ..]);
+            spec.at_least.insert("muse".to_string(), 3);
+        }, GroupTooSmall("muse".to_string()));
+        check(&|spec| {
+            for slot in 0 .. 8 {
+                spec.lock[slot] = Some(ordinal(slot));
+            }
+            spec.require = vec![ordinal(8), ordinal(9)];
+        }, TooManyCards);
+        check(&|spec| spec.ban_cards = ordinals(&glob, &(8 .. glob.album.len()).collect::<Vec<_>>()), TooFewCards);
+        check(&|spec| spec.ban_accs = (8 .. glob.inventory.len()).map(AccRef::Index).collect(), TooFewAccs);
+        check(&|spec| spec.ban_accs = vec![AccRef::Index(glob.inventory.len())], NoSuchAcc(glob.inventory.len()));
+        check(&|spec| {
+            spec.lock = [0, 1, 2, 3, 4, 5, 6, 7, 8].map(|card_i| Some(ordinal(card_i)));
+            spec.ban_accs = (9 .. glob.inventory.len()).map(AccRef::Index).collect();
+        }, NothingToSearch);
+    }
+
+    // with some kinds of moves turned off, constraints that leave plenty to search can leave nothing
+    #[test]
+    fn nothing_to_search_under_weights() {
+        let mut glob = testing::glob(120);
+        let mut spec = ConstraintSpec {
+            lock: [0, 1, 2, 3, 4, 5, 6, 7, 8].map(|card_i| Some(glob.album[card_i].ordinal)),
+            ..ConstraintSpec::default()
+        };
+        assert!(spec.to_constraints(&glob).is_ok());
+        glob.moves = MoveWeights::parse("acc=0").unwrap();
+        assert_eq!(spec.to_constraints(&glob), Err(NothingToSearch));
+        glob.moves = MoveWeights::parse("acc=0,acc-swap=1").unwrap();
+        assert!(spec.to_constraints(&glob).is_ok());
+        // one open slot: nothing to swap it with, and with card=0 nothing to put in it
+        spec.lock[4] = None;
+        glob.moves = MoveWeights::parse("card=0,acc=0").unwrap();
+        assert_eq!(spec.to_constraints(&glob), Err(NothingToSearch));
+        glob.moves = MoveWeights::parse("acc=0").unwrap();
+        assert!(spec.to_constraints(&glob).is_ok());
+    }
+}
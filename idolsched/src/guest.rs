@@ -0,0 +1,23 @@
+use local_search::ReusableBuffer;
+use card_consumer::json_card::TrimCard;
+use super::PlayGlob;
+use super::card::Card;
+use super::schedule::Schedule;
+use super::live_show::Status;
+
+// the voltage each of `candidates` would add to `team` as its guest (over having no guest),
+// at `lb` and with or without its skill tree. the team stays as it is.
+// returns (ordinal, gain), best first.
+pub fn rank_guests(glob: &PlayGlob, team: &Schedule, candidates: &[&TrimCard], lb: u8, fed: bool) -> Vec<(u32, f64)> {
+    let mut buf = Status::create();
+    let base = glob.with_guest(None).est_voltage(team, &mut buf);
+    let mut ranking: Vec<(u32, f64)> = candidates.iter()
+        .map(|jc| {
+            let guest = Card::instantiate_json(jc, lb, fed);
+            buf.refresh();
+            (jc.ordinal, glob.with_guest(Some(guest)).est_voltage(team, &mut buf) - base)
+        })
+        .collect();
+    ranking.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    ranking
+}
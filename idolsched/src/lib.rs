@@ -4,33 +4,123 @@ pub mod acct_info;
 pub mod card;
 pub mod accessory;
 pub mod schedule;
+pub mod team;
+pub mod constraint;
+pub mod multi;
+pub mod advice;
+pub mod scout;
+pub mod sensitivity;
+pub mod guest;
 pub mod skill;
+pub mod report;
 mod live_show;
+#[cfg(test)]
+mod testing;
 
 use std::collections::BTreeMap;
+use std::sync::Arc;
+use serde::Serialize;
+use sifas_data::prelude::Attribute;
 use card_consumer::json_card::TrimCard;
 use card::Card;
 use accessory::Acc;
 use acct_info::AcctInfo;
+use constraint::Constraints;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct PlayGlob {
     pub song: mapdb::Song,
     pub album: Vec<card::Card>,
     pub inventory: Vec<accessory::Acc>,
+    pub constraints: Arc<Constraints>, // shared, since every ScheduleIterator needs them
+    pub guest: Option<Card>, // whose passives apply to the team, as in a live with a friend's card
+    pub moves: schedule::MoveWeights, // which moves the search makes, see ScheduleIterator
 }
 
 impl PlayGlob {
+    pub fn with_attribute(&self, att: Attribute) -> PlayGlob {
+        let mut glob = self.clone();
+        glob.song.override_attribute(Some(att));
+        glob
+    }
+
+    pub fn with_song(&self, song: mapdb::Song) -> PlayGlob {
+        PlayGlob { song, ..self.clone() }
+    }
+
+    pub fn with_guest(&self, guest: Option<Card>) -> PlayGlob {
+        PlayGlob { guest, ..self.clone() }
+    }
+
+    pub fn with_constraints(&self, constraints: Constraints) -> PlayGlob {
+        let mut glob = self.clone();
+        glob.constraints = Arc::new(constraints);
+        glob
+    }
+
+    // the same glob with the default moves always on (see MoveWeights::with_defaults), to hill climb with
+    pub fn for_climbing(&self) -> PlayGlob {
+        PlayGlob { moves: self.moves.with_defaults(), ..self.clone() }
+    }
+
+    // the same glob with `card` added to the end of the album, e.g. to try out a card you don't have
+    pub fn with_card(&self, card: Card) -> PlayGlob {
+        let mut glob = self.clone();
+        glob.constraints = Arc::new(self.constraints.with_card(&card));
+        glob.album.push(card);
+        glob
+    }
+
     pub fn est_voltage(&self, sched: &schedule::Schedule, status: &mut live_show::Status) -> f64 {
-        live_show::run(&self.song, &self.album, &self.inventory, sched, status)
+        self.est_voltage_fatigued(sched, &[0.0; 9], status)
+    }
+
+    // `fatigue` is added to the fatigue of the card in each position,
+    // e.g. for cards that were used on earlier floors of the same tower run
+    pub fn est_voltage_fatigued(&self, sched: &schedule::Schedule, fatigue: &[f64; 9], status: &mut live_show::Status)
+    -> f64 {
+        live_show::run(self, sched, fatigue, status)
+    }
+
+    pub fn trace_voltage(&self, sched: &schedule::Schedule) -> report::Trace {
+        self.trace_voltage_fatigued(sched, &[0.0; 9])
+    }
+
+    pub fn trace_voltage_fatigued(&self, sched: &schedule::Schedule, fatigue: &[f64; 9]) -> report::Trace {
+        use local_search::ReusableBuffer;
+        let mut status = live_show::Status::create();
+        let mut trace = report::Trace::default();
+        live_show::run_traced(self, sched, fatigue, &mut status, &mut trace);
+        trace
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct ScheduleDisplayInfo {
     pub voltage: f64,
-    pub cards: [u32; 9], // ordinals, RRRGGGBBB
+    pub cards: [u32; 9], // ordinals, GGGBBBRRR
     pub sp3: [u32; 3], // indexes into `cards` (as u32)
     pub accs: [u32; 9], // indexes into the account's info (as u32)
+    pub code: String, // see team::TeamSpec::to_code
+}
+
+impl ScheduleDisplayInfo {
+    pub fn new(glob: &PlayGlob, sched: &schedule::Schedule, energy: f64) -> ScheduleDisplayInfo {
+        let mut cards = [0; 9];
+        for (i, &card_i) in sched.cards.iter().enumerate() {
+            cards[i] = glob.album[card_i].ordinal;
+        }
+        let mut sp3 = [0; 3];
+        for (i, &v) in sched.sp3.iter().enumerate() {
+            sp3[i] = v as u32;
+        }
+        let mut accs = [0; 9];
+        for (i, &v) in sched.accs.iter().enumerate() {
+            accs[i] = v as u32;
+        }
+        let code = team::TeamSpec::from_schedule(sched, glob).to_code();
+        ScheduleDisplayInfo { voltage: -energy, cards, sp3, accs, code }
+    }
 }
 
 pub fn init_acct(acct_json: &str) -> Result<AcctInfo, serde_json::Error> {
@@ -39,17 +129,53 @@ pub fn init_acct(acct_json: &str) -> Result<AcctInfo, serde_json::Error> {
     Ok(acct)
 }
 
-pub fn init_glob(card_details: &BTreeMap<u32, TrimCard>, acct: &AcctInfo, song_id: u32, song_json: &str)
--> Result<PlayGlob, serde_json::Error> {
-    let song = mapdb::parse_song(song_id, song_json)?;
+pub fn init_glob(
+    card_details: &BTreeMap<u32, TrimCard>, acct: &AcctInfo,
+    song_id: u32, song_json: &str, att_override: Option<Attribute>
+) -> Result<PlayGlob, serde_json::Error> {
+    let mut song = mapdb::parse_song(song_id, song_json)?;
+    song.override_attribute(att_override);
     let mut album = Vec::new();
     for (ordinal, jcard) in card_details.iter() {
         if let Some(card_inf) = acct.album.get(ordinal) {
-            let card = Card::instantiate_json(&jcard, card_inf.lb, card_inf.idolized);
+            let mut card = Card::instantiate_json(jcard, card_inf.lb, card_inf.idolized);
+            card.fatigue = card_inf.fatigue.min(100) as f64 / 100.0;
             album.push(card);
         }
     }
     let inventory = acct.accs.iter().map(|info| Acc::from_info(info)).collect();
-    Ok(PlayGlob { album, inventory, song })
+    Ok(PlayGlob { album, inventory, song, constraints: Arc::default(), guest: None, moves: Default::default() })
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use local_search::ReusableBuffer;
+    use live_show::Status;
+    use schedule::Schedule;
+
+    // the first nine cards in the album of attribute `att`
+    fn team_of(glob: &PlayGlob, att: Attribute) -> Schedule {
+        let cards: Vec<usize> = (0 .. glob.album.len()).filter(|&card_i| glob.album[card_i].attribute == att).collect();
+        let mut sched = Schedule::default();
+        sched.cards.copy_from_slice(&cards[.. 9]);
+        sched
+    }
+
+    #[test]
+    fn attribute_override() {
+        let glob = testing::glob(120);
+        assert_eq!(glob.song.attribute, Attribute::Active);
+        let smile = init_glob(testing::cards(), &testing::acct(), 10015301, &testing::song_json(120), Some(Attribute::Smile)).unwrap();
+        assert_eq!(smile, glob.with_attribute(Attribute::Smile));
+        assert_eq!(smile.song.default_attribute, Attribute::Active);
+        assert_eq!(glob.with_attribute(Attribute::Active), glob);
+
+        // a team does better on a song of its own attribute than with the song turned into another
+        let volts = |glob: &PlayGlob, sched: &Schedule| glob.est_voltage(sched, &mut Status::create());
+        let (smile_team, active_team) = (team_of(&glob, Attribute::Smile), team_of(&glob, Attribute::Active));
+        assert!(volts(&smile, &smile_team) > volts(&glob, &smile_team));
+        assert!(volts(&glob, &active_team) > volts(&smile, &active_team));
+    }
+}
@@ -7,6 +7,7 @@ use sifas_data::prelude::*;
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Song {
     pub default_attribute: Attribute,
+    pub attribute: Attribute, // default_attribute unless overridden
     pub target_voltage: u32,
     pub lose_at_death: bool,
     pub sp_gauge_length: u32,
@@ -16,6 +17,24 @@ pub struct Song {
     pub skill_voltage_upper_limit: u32,
     pub squad_change_voltage_upper_limit: u32,
     pub kt_notes: usize,
+    pub floor_type: FloorType,
+}
+
+// only tower floors tire cards out; see Card::fatigue.
+// parse_song can't tell which a song is: the beatmap JSON doesn't say, and the one digit of the ID
+// it does read (the live type, for lose_at_death) isn't known to set tower floors apart,
+// since every song in ui_web/js/songlist.json is a free live. so songs come out Free
+// and it's up to the caller to set floor_type, as ui_cli's --tower does.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FloorType {
+    Free,
+    Tower,
+}
+
+impl Song {
+    pub fn override_attribute(&mut self, att: Option<Attribute>) {
+        self.attribute = att.unwrap_or(self.default_attribute);
+    }
 }
 
 pub fn parse_song(id: u32, json: &str) -> Result<Song, serde_json::Error> {
@@ -40,6 +59,7 @@ pub fn parse_song(id: u32, json: &str) -> Result<Song, serde_json::Error> {
     };
     Ok(Song {
         default_attribute: jsong.song_attribute,
+        attribute: jsong.song_attribute,
         target_voltage: jsong.ranks.rank_s,
         lose_at_death: live_type < 4,
         sp_gauge_length,
@@ -49,6 +69,7 @@ pub fn parse_song(id: u32, json: &str) -> Result<Song, serde_json::Error> {
         skill_voltage_upper_limit,
         squad_change_voltage_upper_limit,
         kt_notes: jsong.notes.len(),
+        floor_type: FloorType::Free,
     })
 }
 
@@ -84,3 +105,26 @@ struct JsonNote {
     pub time: u32,
     pub gimmick: Option<usize>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn parses_as_a_free_live() {
+        let mut song = parse_song(10015301, &testing::song_json(20)).unwrap();
+        assert_eq!(song.floor_type, FloorType::Free);
+        assert_eq!(song.kt_notes, 20);
+        assert_eq!((song.target_voltage, song.note_stamina_reduce, song.sp_gauge_length), (1000000, 300, 6000));
+        assert!(song.lose_at_death);
+        assert_eq!(song.attribute, song.default_attribute);
+        song.override_attribute(Some(Attribute::Pure));
+        assert_eq!(song.attribute, Attribute::Pure);
+        song.override_attribute(None);
+        assert_eq!(song.attribute, song.default_attribute);
+        // adv+ has a longer sp gauge and higher voltage caps
+        let plus = parse_song(10015401, &testing::song_json(20)).unwrap();
+        assert_eq!((plus.sp_gauge_length, plus.note_voltage_upper_limit), (7200, 150_000));
+    }
+}
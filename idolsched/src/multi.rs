@@ -0,0 +1,262 @@
+use local_search::SearchState;
+use rand::Rng;
+use super::PlayGlob;
+use super::mapdb::Song;
+use super::schedule::{Schedule, ScheduleIterator};
+use super::live_show::Status;
+
+// a set of songs to build teams for all at once, like the floors of a tower,
+// where every team comes out of the same album and inventory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiGlob {
+    pub floors: Vec<PlayGlob>, // one per song; all with the same album, inventory and constraints
+    pub max_uses: Option<usize>, // how many of the teams a card may be on; None for no limit
+    pub objective: Objective,
+    // fatigue a card picks up from each earlier floor it's used on,
+    // which only matters on floors of type FloorType::Tower
+    pub fatigue_per_use: f64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Objective {
+    Total, // as much voltage as possible over all the songs
+    Worst, // as much voltage as possible on the weakest song, for when they all have to be cleared
+}
+
+impl MultiGlob {
+    pub fn new(glob: &PlayGlob, songs: Vec<Song>, max_uses: Option<usize>, objective: Objective) -> MultiGlob {
+        let floors = songs.into_iter().map(|song| glob.with_song(song)).collect();
+        MultiGlob { floors, max_uses, objective, fatigue_per_use: 0.0 }
+    }
+
+    pub fn voltages(&self, plan: &Plan, buf: &mut Status) -> Vec<f64> {
+        let mut uses = vec![0; self.floors[0].album.len()];
+        self.floors.iter().zip(plan.teams.iter())
+            .map(|(floor, team)| {
+                let fatigue = self.fatigue(team, &mut uses);
+                floor.est_voltage_fatigued(team, &fatigue, buf)
+            })
+            .collect()
+    }
+
+    // the extra fatigue of each card in `team` from the floors before it, given how often
+    // each card was used on those floors. adds `team` to `uses` for the floors after.
+    pub fn fatigue(&self, team: &Schedule, uses: &mut [usize]) -> [f64; 9] {
+        let mut fatigue = [0.0; 9];
+        for (f, &card_i) in fatigue.iter_mut().zip(team.cards.iter()) {
+            *f = self.fatigue_per_use * uses[card_i] as f64;
+            uses[card_i] += 1;
+        }
+        fatigue
+    }
+
+    fn uses(&self, plan: &Plan) -> Vec<usize> {
+        let mut uses = vec![0; self.floors[0].album.len()];
+        for team in plan.teams.iter() {
+            for &card_i in team.cards.iter() {
+                uses[card_i] += 1;
+            }
+        }
+        uses
+    }
+}
+
+// one team per song, in the same order as MultiGlob::floors
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Plan {
+    pub teams: Vec<Schedule>,
+}
+
+impl Plan {
+    // random teams, one song at a time, leaving out cards that are already on as many teams as allowed.
+    // that can paint itself into a corner (e.g. a required card can't be on every team),
+    // so it gives up after a few tries.
+    pub fn new_random<R: Rng + ?Sized>(rng: &mut R, mglob: &MultiGlob) -> Option<Plan> {
+        const TRIES: usize = 20;
+        let album_size = mglob.floors[0].album.len();
+        'tries: for _ in 0 .. TRIES {
+            let mut uses = vec![0; album_size];
+            let mut teams = Vec::with_capacity(mglob.floors.len());
+            for floor in mglob.floors.iter() {
+                let unavailable: Vec<bool> = match mglob.max_uses {
+                    Some(max) => uses.iter().map(|&n| n >= max).collect(),
+                    None => Vec::new(),
+                };
+                let team = match Schedule::new_random_avoiding(rng, floor, &unavailable) {
+                    Some(team) => team,
+                    None => continue 'tries,
+                };
+                for &card_i in team.cards.iter() {
+                    uses[card_i] += 1;
+                }
+                teams.push(team);
+            }
+            return Some(Plan { teams });
+        }
+        None
+    }
+}
+
+// every plan one move away: a move on one team, as for a single song,
+// leaving out any that would put a card on more teams than allowed.
+// like ScheduleIterator, nth gives None for moves that aren't allowed.
+pub struct PlanIterator {
+    teams: Vec<Schedule>,
+    floors: Vec<ScheduleIterator>,
+    floor: usize,
+    uses: Vec<usize>,
+    max_uses: Option<usize>,
+}
+
+impl PlanIterator {
+    fn allowed(&self, floor: usize, team: &Schedule) -> bool {
+        let max = match self.max_uses {
+            Some(max) => max,
+            None => return true,
+        };
+        let old = &self.teams[floor].cards;
+        team.cards.iter().all(|card_i| old.contains(card_i) || self.uses[*card_i] < max)
+    }
+
+    fn with_team(&self, floor: usize, team: Schedule) -> Plan {
+        let mut teams = self.teams.clone();
+        teams[floor] = team;
+        Plan { teams }
+    }
+}
+
+impl Iterator for PlanIterator {
+    type Item = Plan;
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let upper = self.floors[self.floor.min(self.floors.len()) ..].iter()
+            .map(|it| it.size_hint().1.unwrap_or(0))
+            .sum();
+        (0, Some(upper))
+    }
+
+    fn next(&mut self) -> Option<Plan> {
+        while self.floor < self.floors.len() {
+            match self.floors[self.floor].next() {
+                Some(team) => if self.allowed(self.floor, &team) {
+                    return Some(self.with_team(self.floor, team));
+                },
+                None => self.floor += 1,
+            }
+        }
+        None
+    }
+
+    fn nth(&mut self, mut n: usize) -> Option<Plan> {
+        while self.floor < self.floors.len() {
+            let left = self.floors[self.floor].size_hint().1.unwrap_or(0);
+            if n < left {
+                let floor = self.floor;
+                let team = self.floors[floor].nth(n)?;
+                return if self.allowed(floor, &team) {
+                    Some(self.with_team(floor, team))
+                } else {
+                    None
+                };
+            }
+            n -= left;
+            self.floor += 1;
+        }
+        None
+    }
+}
+
+impl SearchState for Plan {
+    type Glob = MultiGlob;
+    type Buf = Status;
+    type Iter = PlanIterator;
+    fn energy(&self, mglob: &MultiGlob, buf: &mut Status) -> f64 {
+        let voltages = mglob.voltages(self, buf);
+        let score = match mglob.objective {
+            Objective::Total => voltages.iter().sum(),
+            Objective::Worst => voltages.iter().cloned().fold(f64::INFINITY, f64::min),
+        };
+        -score
+    }
+
+    fn successors(&self, mglob: &MultiGlob) -> PlanIterator {
+        PlanIterator {
+            teams: self.teams.clone(),
+            floors: mglob.floors.iter().zip(self.teams.iter())
+                .map(|(floor, team)| team.successors(floor))
+                .collect(),
+            floor: 0,
+            uses: mglob.uses(self),
+            max_uses: mglob.max_uses,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use local_search::{PortableRng, ReusableBuffer};
+    use crate::constraint::ConstraintSpec;
+    use crate::{mapdb, testing};
+
+    // two songs of different lengths; max_uses is what --reuse gives
+    fn mglob(glob: &PlayGlob, max_uses: Option<usize>) -> MultiGlob {
+        let songs = [20, 30].iter()
+            .map(|&notes| mapdb::parse_song(10015301, &testing::song_json(notes)).unwrap())
+            .collect();
+        MultiGlob::new(glob, songs, max_uses, Objective::Total)
+    }
+
+    fn shared(plan: &Plan) -> usize {
+        9 - plan.teams[0].cards_differing(&plan.teams[1])
+    }
+
+    #[test]
+    fn once_each_unless_reused() {
+        let glob = testing::glob(20);
+        let mut rng = PortableRng::seed_from_u64(43);
+        let once = mglob(&glob, Some(1));
+        let plan = Plan::new_random(&mut rng, &once).unwrap();
+        assert_eq!(shared(&plan), 0);
+        // the second team taking the first team's first card
+        let mut sharing = plan.clone();
+        sharing.teams[1].cards[0] = plan.teams[0].cards[0];
+        assert!(!plan.successors(&once).any(|p| p == sharing));
+        let total = plan.successors(&once).size_hint().1.unwrap();
+        for n in 0 .. total {
+            if let Some(p) = plan.successors(&once).nth(n) {
+                assert_eq!(shared(&p), 0, "{:?}", p);
+            }
+        }
+        for max_uses in [Some(2), None] {
+            assert!(plan.successors(&mglob(&glob, max_uses)).any(|p| p == sharing));
+        }
+    }
+
+    #[test]
+    fn required_card_needs_reuse() {
+        let glob = testing::glob(20);
+        let spec = ConstraintSpec { require: vec![glob.album[5].ordinal], ..ConstraintSpec::default() };
+        let glob = glob.with_constraints(spec.to_constraints(&glob).unwrap());
+        let mut rng = PortableRng::seed_from_u64(43);
+        assert_eq!(Plan::new_random(&mut rng, &mglob(&glob, Some(1))), None);
+        let plan = Plan::new_random(&mut rng, &mglob(&glob, Some(2))).unwrap();
+        assert!(plan.teams.iter().all(|team| team.cards.contains(&5)));
+    }
+
+    #[test]
+    fn objectives() {
+        let glob = testing::glob(20);
+        let mut rng = PortableRng::seed_from_u64(43);
+        let mut mglob = mglob(&glob, None);
+        let plan = Plan::new_random(&mut rng, &mglob).unwrap();
+        let mut buf = Status::create();
+        let voltages = mglob.voltages(&plan, &mut buf);
+        assert_eq!(voltages[0], mglob.floors[0].est_voltage(&plan.teams[0], &mut buf));
+        assert!(voltages[1] > voltages[0]);
+        assert_eq!(plan.energy(&mglob, &mut buf), -(voltages[0] + voltages[1]));
+        mglob.objective = Objective::Worst;
+        assert_eq!(plan.energy(&mglob, &mut buf), -voltages[0]);
+    }
+}
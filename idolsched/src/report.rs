@@ -0,0 +1,197 @@
+use serde::Serialize;
+
+// everything live_show::run_traced knows about a single note
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct NoteTrace {
+    pub note: usize,
+    pub strat: usize,
+    pub card_pos: usize,
+    pub base_appeal: f64, // appeal after passives and accessories, before buffs
+    pub appeal: f64,
+    pub buff_appeal_add: f64,
+    pub buff_appeal: f64,
+    pub buff_appeal_ex: f64,
+    pub buff_tapvo: f64,
+    pub tap_voltage: f64, // after the cap
+    pub crit_voltage: f64, // expected share of tap_voltage that comes from crits
+    pub tap_capped: bool,
+    pub skill_voltage: f64,
+    pub skill_capped: bool,
+    pub heal: f64,
+    pub shield_gain: f64, // negative if the note's skill removed shield
+    pub damage: f64, // stamina lost, i.e. after the shield soaked up what it could
+    pub stamina: f64,
+    pub shield: f64,
+}
+
+impl NoteTrace {
+    pub fn is_buffed(&self) -> bool {
+        self.buff_appeal_add != 0.0 || self.buff_appeal != 0.0
+        || self.buff_appeal_ex != 0.0 || self.buff_tapvo != 0.0
+    }
+}
+
+pub trait Tracer {
+    fn start(&mut self, _max_stam: f64) {}
+    fn note(&mut self, _nt: NoteTrace) {}
+    fn end(&mut self, _voltage: f64, _died: bool) {}
+}
+
+impl Tracer for () {}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Trace {
+    pub max_stamina: f64,
+    pub voltage: f64, // real voltage, without the death penalty run() applies
+    pub died: bool,
+    pub notes: Vec<NoteTrace>,
+}
+
+impl Tracer for Trace {
+    fn start(&mut self, max_stam: f64) {
+        self.max_stamina = max_stam;
+        self.notes.clear();
+    }
+
+    fn note(&mut self, nt: NoteTrace) {
+        self.notes.push(nt);
+    }
+
+    fn end(&mut self, voltage: f64, died: bool) {
+        self.voltage = voltage;
+        self.died = died;
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize)]
+pub struct Contribution {
+    pub notes: usize,
+    pub buffed_notes: usize,
+    pub tap: f64, // includes crit
+    pub crit: f64,
+    pub skill: f64,
+    pub heal: f64,
+    pub shield: f64,
+    pub damage: f64,
+    pub tap_caps: usize,
+    pub skill_caps: usize,
+}
+
+impl Contribution {
+    pub fn voltage(&self) -> f64 {
+        self.tap + self.skill
+    }
+
+    pub fn appeal_voltage(&self) -> f64 {
+        self.tap - self.crit
+    }
+
+    fn add(&mut self, nt: &NoteTrace) {
+        self.notes += 1;
+        if nt.is_buffed() {
+            self.buffed_notes += 1;
+        }
+        self.tap += nt.tap_voltage;
+        self.crit += nt.crit_voltage;
+        self.skill += nt.skill_voltage;
+        self.heal += nt.heal;
+        self.shield += nt.shield_gain;
+        self.damage += nt.damage;
+        if nt.tap_capped {
+            self.tap_caps += 1;
+        }
+        if nt.skill_capped {
+            self.skill_caps += 1;
+        }
+    }
+}
+
+// per-card, per-strategy and overall summaries of a trace.
+// by_card is indexed by formation position, like Schedule::cards.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Report {
+    pub voltage: f64,
+    pub died_at: Option<usize>,
+    pub max_stamina: f64,
+    pub final_stamina: f64,
+    pub by_card: [Contribution; 9],
+    pub by_strat: [Contribution; 3],
+    pub total: Contribution,
+}
+
+impl Report {
+    pub fn from_trace(trace: &Trace) -> Report {
+        let mut report = Report {
+            voltage: trace.voltage,
+            died_at: if trace.died { trace.notes.last().map(|nt| nt.note) } else { None },
+            max_stamina: trace.max_stamina,
+            final_stamina: trace.notes.last().map(|nt| nt.stamina).unwrap_or(trace.max_stamina),
+            .. Report::default()
+        };
+        for nt in trace.notes.iter() {
+            report.by_card[nt.card_pos].add(nt);
+            report.by_strat[nt.strat].add(nt);
+            report.total.add(nt);
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use local_search::{PortableRng, ReusableBuffer};
+    use crate::live_show::Status;
+    use crate::schedule::Schedule;
+    use crate::testing;
+
+    fn note(note: usize, strat: usize, card_pos: usize, tap_voltage: f64, skill_voltage: f64) -> NoteTrace {
+        NoteTrace { note, strat, card_pos, tap_voltage, skill_voltage, ..NoteTrace::default() }
+    }
+
+    #[test]
+    fn sums_notes_by_card_and_strategy() {
+        let trace = Trace {
+            max_stamina: 100.0,
+            voltage: 60.0,
+            died: true,
+            notes: vec![
+                NoteTrace { crit_voltage: 4.0, tap_capped: true, stamina: 90.0, ..note(0, 0, 1, 10.0, 0.0) },
+                NoteTrace { buff_appeal: 0.1, damage: 10.0, stamina: 80.0, ..note(1, 0, 2, 10.0, 5.0) },
+                NoteTrace { heal: 3.0, skill_capped: true, stamina: 0.0, ..note(2, 1, 4, 15.0, 20.0) },
+            ],
+        };
+        let report = Report::from_trace(&trace);
+        assert_eq!((report.voltage, report.died_at, report.max_stamina, report.final_stamina), (60.0, Some(2), 100.0, 0.0));
+        assert_eq!(report.by_card[1], Contribution { notes: 1, tap: 10.0, crit: 4.0, tap_caps: 1, ..Contribution::default() });
+        assert_eq!(report.by_card[2], Contribution {
+            notes: 1, buffed_notes: 1, tap: 10.0, skill: 5.0, damage: 10.0, ..Contribution::default()
+        });
+        assert_eq!(report.by_card[0], Contribution::default());
+        assert_eq!((report.by_strat[0].notes, report.by_strat[0].voltage()), (2, 25.0));
+        assert_eq!((report.by_strat[1].notes, report.by_strat[1].heal, report.by_strat[1].skill_caps), (1, 3.0, 1));
+        assert_eq!(report.by_strat[2], Contribution::default());
+        assert_eq!((report.total.notes, report.total.voltage(), report.total.appeal_voltage()), (3, 60.0, 31.0));
+        // nobody died, so the stamina left is the last note's
+        let report = Report::from_trace(&Trace { died: false, ..trace });
+        assert_eq!((report.died_at, report.final_stamina), (None, 0.0));
+    }
+
+    #[test]
+    fn adds_up_to_the_live_show() {
+        let glob = testing::glob(120);
+        let mut rng = PortableRng::seed_from_u64(27);
+        let sched = Schedule::new_random(&mut rng, &glob);
+        let trace = glob.trace_voltage(&sched);
+        assert_eq!(trace.notes.len(), 120);
+        assert!(!trace.died);
+        assert_eq!(trace.voltage, glob.est_voltage(&sched, &mut Status::create()));
+        let report = Report::from_trace(&trace);
+        assert!((report.total.voltage() - report.voltage).abs() < 1e-6 * report.voltage);
+        let by_card: f64 = report.by_card.iter().map(Contribution::voltage).sum();
+        let by_strat: usize = report.by_strat.iter().map(|c| c.notes).sum();
+        assert!((by_card - report.voltage).abs() < 1e-6 * report.voltage);
+        assert_eq!(by_strat, 120);
+    }
+}
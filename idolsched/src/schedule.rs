@@ -1,9 +1,10 @@
-use local_search::SearchState;
+use local_search::{SearchState, MoveAttrs, Crossover};
+use local_search::portable::{self, UniformIndex};
+use std::sync::Arc;
 use super::PlayGlob;
+use super::constraint::Constraints;
 use super::live_show::Status;
 use rand::Rng;
-use rand::distributions::Uniform;
-use rand::seq::SliceRandom;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Schedule {
@@ -23,67 +24,279 @@ impl Default for Schedule {
 }
 
 impl Schedule {
-    pub fn new_random<R: Rng + ?Sized>(rng: &mut R, album_size: usize, inv_size: usize) -> Schedule {
+    // how many of this team's cards aren't on `other`, e.g. for telling teams apart in local_search::top::TopK
+    pub fn cards_differing(&self, other: &Schedule) -> usize {
+        self.cards.iter().filter(|card_i| !other.cards.contains(card_i)).count()
+    }
+
+    // a random team that follows glob.constraints
+    pub fn new_random<R: Rng + ?Sized>(rng: &mut R, glob: &PlayGlob) -> Schedule {
+        // ConstraintSpec::to_constraints made sure there is one
+        Schedule::new_random_avoiding(rng, glob, &[]).unwrap()
+    }
+
+    // the same, but leaving out the album cards marked in `unavailable`.
+    // None if that leaves no team that follows the constraints.
+    pub fn new_random_avoiding<R: Rng + ?Sized>(rng: &mut R, glob: &PlayGlob, unavailable: &[bool])
+    -> Option<Schedule> {
+        let album_size = glob.album.len();
+        let inv_size = glob.inventory.len();
         assert!(album_size >= 9);
         assert!(inv_size < 0x8000);
-        let mut cards = [0; 9];
+        let cards = glob.constraints.random_cards(rng, album_size, unavailable)?;
         let sp3 = [1, 0, 2];
         let mut accs = [0; 9];
-        let card_dist = Uniform::new(0, album_size);
-        let acc_dist = Uniform::new(0, inv_size);
-        let mut used_flags = Vec::with_capacity(album_size.max(inv_size));
+        let acc_dist = UniformIndex::new(inv_size);
+        let mut used_flags = vec![false; inv_size];
 
-        used_flags.resize(album_size, false);
-        for i in 0 .. 9 {
-            let mut v = rng.sample(card_dist);
-            while used_flags[v] {
-                v = rng.sample(card_dist);
-            }
-            cards[i] = v;
-            used_flags[v] = true;
-        }
-
-        used_flags.truncate(0);
-        used_flags.resize(inv_size, false);
         for i in 0 .. 9 {
             if i == inv_size {
-                accs[..].shuffle(rng);
+                portable::shuffle(rng, &mut accs[..]);
                 break;
             }
             let mut v = rng.sample(acc_dist);
-            while used_flags[v] {
+            while used_flags[v] || !glob.constraints.acc_allowed(v) {
                 v = rng.sample(acc_dist);
             }
             accs[i] = v;
             used_flags[v] = true;
         }
 
-        Schedule { cards, accs, sp3 }
+        Some(Schedule { cards, accs, sp3 })
+    }
+}
+
+// how often each kind of move comes up, relative to the others: a move of a kind with weight 2
+// is twice as likely to be picked at random (as anneal::random_succ does) as a move of a kind with weight 1.
+// a kind with weight 0 is left out entirely, even from searches that try every move, like tabu search
+// (hill climbing a found team uses with_defaults, though). weights are at most u16::MAX,
+// so that a kind's move count times its weight can't overflow.
+// the default is the original move set, where only swaps involving green matter, since on autoplay
+// nothing else about the order of the cards does.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MoveWeights {
+    pub green_swap: u16, // a green card with a card from another strategy
+    pub card: u16, // a card on the team for one in the album that isn't
+    pub acc: u16, // an accessory on the team for one in the inventory that isn't
+    pub back_swap: u16, // a blue card with a red card
+    pub formation_swap: u16, // two cards in the same strategy, which changes who skills targeting a position reach
+    pub strat_swap: u16, // two whole strategies, cards and accessories
+    pub acc_swap: u16, // accessories in two different strategies
+}
+
+impl Default for MoveWeights {
+    fn default() -> MoveWeights {
+        MoveWeights { green_swap: 1, card: 1, acc: 1, back_swap: 0, formation_swap: 0, strat_swap: 0, acc_swap: 0 }
+    }
+}
+
+impl MoveWeights {
+    // e.g. "back=1,formation=2,card=0"; kinds that aren't mentioned keep their default weight
+    pub fn parse(text: &str) -> Option<MoveWeights> {
+        let mut weights = MoveWeights::default();
+        for part in text.split(',') {
+            let mut halves = part.splitn(2, '=');
+            let (name, weight) = (halves.next()?.trim(), halves.next()?.trim().parse().ok()?);
+            let kind = MOVE_KINDS.iter().find(|&&kind| kind.name() == name)?;
+            *weights.get_mut(*kind) = weight;
+        }
+        Some(weights).filter(|weights| MOVE_KINDS.iter().any(|&kind| weights.get(kind) > 0))
+    }
+
+    // these weights with the default kinds turned back on, for hill climbing a team at the end:
+    // it tries every move anyway, and saying no single change improves a team
+    // shouldn't depend on which kinds the search itself made
+    pub fn with_defaults(self) -> MoveWeights {
+        let default = MoveWeights::default();
+        MoveWeights {
+            green_swap: self.green_swap.max(default.green_swap),
+            card: self.card.max(default.card),
+            acc: self.acc.max(default.acc),
+            ..self
+        }
+    }
+
+    fn get(&self, kind: MoveKind) -> usize {
+        usize::from(match kind {
+            MoveKind::GreenSwap => self.green_swap,
+            MoveKind::Card => self.card,
+            MoveKind::Acc => self.acc,
+            MoveKind::BackSwap => self.back_swap,
+            MoveKind::FormationSwap => self.formation_swap,
+            MoveKind::StratSwap => self.strat_swap,
+            MoveKind::AccSwap => self.acc_swap,
+        })
+    }
+
+    fn get_mut(&mut self, kind: MoveKind) -> &mut u16 {
+        match kind {
+            MoveKind::GreenSwap => &mut self.green_swap,
+            MoveKind::Card => &mut self.card,
+            MoveKind::Acc => &mut self.acc,
+            MoveKind::BackSwap => &mut self.back_swap,
+            MoveKind::FormationSwap => &mut self.formation_swap,
+            MoveKind::StratSwap => &mut self.strat_swap,
+            MoveKind::AccSwap => &mut self.acc_swap,
+        }
     }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum MoveKind {
+    GreenSwap,
+    Card,
+    Acc,
+    BackSwap,
+    FormationSwap,
+    StratSwap,
+    AccSwap,
+}
+
+// in the order ScheduleIterator numbers them. the original three come first,
+// so that the default weights number moves just as before and old seeds give the same teams
+const MOVE_KINDS: [MoveKind; 7] = [
+    MoveKind::GreenSwap, MoveKind::Card, MoveKind::Acc,
+    MoveKind::BackSwap, MoveKind::FormationSwap, MoveKind::StratSwap, MoveKind::AccSwap,
+];
+
+// pairs of positions within a strategy, or pairs of strategies
+const PAIRS: [(usize, usize); 3] = [(0, 1), (0, 2), (1, 2)];
+
+impl MoveKind {
+    fn name(self) -> &'static str {
+        match self {
+            MoveKind::GreenSwap => "green",
+            MoveKind::Card => "card",
+            MoveKind::Acc => "acc",
+            MoveKind::BackSwap => "back",
+            MoveKind::FormationSwap => "formation",
+            MoveKind::StratSwap => "strategy",
+            MoveKind::AccSwap => "acc-swap",
+        }
+    }
+}
+
+// every team one move away from a starting team, leaving out any the constraints don't allow.
+// moves are numbered kind by kind, and each move takes up as many numbers ("slots")
+// as its kind's weight, so that nth picks moves in proportion to their weights.
+// next goes through every move once.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ScheduleIterator {
     album_size: usize,
     inv_size: usize,
     cards: [usize; 9],
     accs: [usize; 9],
     sp3: [usize; 3],
-    step: usize,
+    step: usize, // the next slot
+    weights: MoveWeights,
+    constraints: Arc<Constraints>,
 }
 
 impl ScheduleIterator {
-    pub fn from_schedule(sched: &Schedule, album_size: usize, inv_size: usize)
-    -> ScheduleIterator {
+    pub fn from_schedule(sched: &Schedule, glob: &PlayGlob) -> ScheduleIterator {
         ScheduleIterator {
-            album_size,
-            inv_size,
-            cards: sched.cards.clone(),
-            accs: sched.accs.clone(),
-            sp3: sched.sp3.clone(),
+            album_size: glob.album.len(),
+            inv_size: glob.inventory.len(),
+            cards: sched.cards,
+            accs: sched.accs,
+            sp3: sched.sp3,
             step: 0,
+            weights: glob.moves,
+            constraints: glob.constraints.clone(),
+        }
+    }
+
+    fn count(&self, kind: MoveKind) -> usize {
+        match kind {
+            MoveKind::GreenSwap => 3 * 6, // 3 cards in green, 6 to swap them with
+            MoveKind::Card => 9 * (self.album_size - 9),
+            MoveKind::Acc => 9 * (self.inv_size - 9),
+            MoveKind::BackSwap => 3 * 3,
+            MoveKind::FormationSwap => 3 * PAIRS.len(),
+            MoveKind::StratSwap => PAIRS.len(),
+            MoveKind::AccSwap => PAIRS.len() * 3 * 3,
         }
     }
+
+    fn slots(&self) -> usize {
+        MOVE_KINDS.iter().map(|&kind| self.count(kind) * self.weights.get(kind)).sum()
+    }
+
+    // the move that `slot` belongs to: its kind, its number within the kind,
+    // and the first slot of the move after it
+    fn locate(&self, slot: usize) -> Option<(MoveKind, usize, usize)> {
+        let mut start = 0;
+        for &kind in MOVE_KINDS.iter() {
+            let weight = self.weights.get(kind);
+            let end = start + self.count(kind) * weight;
+            if slot < end {
+                let i = (slot - start) / weight;
+                return Some((kind, i, start + (i + 1) * weight));
+            }
+            start = end;
+        }
+        None
+    }
+
+    // move number `i` of `kind`, whether or not it's allowed
+    fn make_move(&self, kind: MoveKind, i: usize) -> Schedule {
+        let mut out_sched = Schedule { sp3: self.sp3, accs: self.accs, cards: self.cards };
+        match kind {
+            MoveKind::GreenSwap => {
+                let slot_green = i / 6;
+                let slot_other = i % 6;
+                out_sched.cards.swap(slot_green, 3 + slot_other);
+            },
+            MoveKind::Card => {
+                let nonteam_cards = self.album_size - 9;
+                let slot = i / nonteam_cards;
+                out_sched.cards[slot] = nth_missing(&self.cards, i % nonteam_cards);
+            },
+            MoveKind::Acc => {
+                let nonteam_accs = self.inv_size - 9;
+                let slot = i / nonteam_accs;
+                out_sched.accs[slot] = nth_missing(&self.accs, i % nonteam_accs);
+            },
+            MoveKind::BackSwap => {
+                out_sched.cards.swap(3 + i / 3, 6 + i % 3);
+            },
+            MoveKind::FormationSwap => {
+                let strat = i / PAIRS.len();
+                let (a, b) = PAIRS[i % PAIRS.len()];
+                out_sched.cards.swap(strat * 3 + a, strat * 3 + b);
+            },
+            MoveKind::StratSwap => {
+                let (a, b) = PAIRS[i];
+                for pos in 0 .. 3 {
+                    out_sched.cards.swap(a * 3 + pos, b * 3 + pos);
+                    out_sched.accs.swap(a * 3 + pos, b * 3 + pos);
+                }
+            },
+            MoveKind::AccSwap => {
+                let (a, b) = PAIRS[i / 9];
+                out_sched.accs.swap(a * 3 + i % 9 / 3, b * 3 + i % 3);
+            },
+        }
+        out_sched
+    }
+}
+
+// the `n`th index (from 0) that isn't in `team`
+fn nth_missing(team: &[usize; 9], n: usize) -> usize {
+    let candidates_needed = n + 1;
+    let mut cand_i = 0;
+    let mut valid_cands = 0;
+    while valid_cands < candidates_needed {
+        if team.contains(&cand_i) {
+            cand_i += 1;
+        } else {
+            valid_cands += 1;
+            if valid_cands < candidates_needed {
+                cand_i += 1;
+            }
+        }
+    }
+    cand_i
 }
 
 impl Default for ScheduleIterator {
@@ -95,6 +308,8 @@ impl Default for ScheduleIterator {
             accs: [0,1,2,3,4,5,6,7,8],
             sp3: [0,1,2],
             step: 0,
+            weights: MoveWeights::default(),
+            constraints: Arc::default(),
         }
     }
 }
@@ -102,85 +317,29 @@ impl Default for ScheduleIterator {
 impl Iterator for ScheduleIterator {
     type Item = Schedule;
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let green_swaps = 3 * 6; // 3 cards in green, 6 to swap them with
-        let nonteam_cards = self.album_size - 9;
-        let card_replacements = 9 * nonteam_cards;
-        let nonteam_accs = self.inv_size - 9;
-        let acc_replacements = 9 * nonteam_accs;
-        let possible_succs = green_swaps + card_replacements + acc_replacements;
-        (0, Some(possible_succs - self.step))
+        (0, Some(self.slots().saturating_sub(self.step)))
     }
 
     fn next(&mut self) -> Option<Schedule> {
-        use std::mem::swap;
-
-        let mut out_sched = Schedule { sp3: self.sp3, accs: self.accs, cards: self.cards };
-
-        let green_swaps = 3 * 6; // 3 cards in green, 6 to swap them with
-        let nonteam_cards = self.album_size - 9;
-        let card_replacements = 9 * nonteam_cards;
-        let nonteam_accs = self.inv_size - 9;
-        let acc_replacements = 9 * nonteam_accs;
-
-        let last_green = green_swaps;
-        let last_card = last_green + card_replacements;
-        let last_acc = last_card + acc_replacements;
-
-        if self.step < last_green {
-            let slot_green = self.step / 6;
-            let slot_other = self.step % 6;
-
-            let (green_strat, other_strats) = out_sched.cards.split_at_mut(3);
-            swap(&mut green_strat[slot_green], &mut other_strats[slot_other]);
-
-            self.step += 1;
-            return Some(out_sched);
-        } else if self.step < last_card {
-            let substep = self.step - last_green;
-            let slot = substep / nonteam_cards;
-            let candidates_needed = substep % nonteam_cards + 1;
-            let mut cand_i = 0;
-            let mut valid_cands = 0;
-            while valid_cands < candidates_needed {
-                if self.cards.iter().any(|curr_i| *curr_i == cand_i) {
-                    cand_i += 1;
-                } else {
-                    valid_cands += 1;
-                    if valid_cands < candidates_needed {
-                        cand_i += 1;
-                    }
-                }
+        while let Some((kind, i, next_step)) = self.locate(self.step) {
+            self.step = next_step;
+            let sched = self.make_move(kind, i);
+            if self.constraints.allows(&sched) {
+                return Some(sched);
             }
-            out_sched.cards[slot] = cand_i;
-            self.step += 1;
-            return Some(out_sched);
-        } else if self.step < last_acc {
-            let substep = self.step - last_card;
-            let slot = substep / nonteam_accs;
-            let candidates_needed = substep % nonteam_accs + 1;
-            let mut cand_i = 0;
-            let mut valid_cands = 0;
-            while valid_cands < candidates_needed {
-                if self.accs.iter().any(|curr_i| *curr_i == cand_i) {
-                    cand_i += 1;
-                } else {
-                    valid_cands += 1;
-                    if valid_cands < candidates_needed {
-                        cand_i += 1;
-                    }
-                }
-            }
-            out_sched.accs[slot] = cand_i;
-            self.step += 1;
-            return Some(out_sched);
-        } else {
-            None
         }
+        None
     }
 
+    // if the n-th slot's move isn't allowed this gives None, rather than the next move that is.
+    // that way picking n at random and retrying on None (as anneal::random_succ does)
+    // is equally likely to land on any allowed move (of the same weight).
     fn nth(&mut self, n: usize) -> Option<Schedule> {
         self.step += n;
-        self.next()
+        let (kind, i, next_step) = self.locate(self.step)?;
+        self.step = next_step;
+        let sched = self.make_move(kind, i);
+        Some(sched).filter(|sched| self.constraints.allows(sched))
     }
 }
 
@@ -193,6 +352,232 @@ impl SearchState for Schedule {
     }
 
     fn successors(&self, glob: &PlayGlob) -> ScheduleIterator {
-        ScheduleIterator::from_schedule(self, glob.album.len(), glob.inventory.len())
+        ScheduleIterator::from_schedule(self, glob)
+    }
+}
+
+// the cards and accessories a move takes off of or puts on the team, or moves around it
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ScheduleAttr {
+    Card(usize),
+    Acc(usize),
+}
+
+impl MoveAttrs for Schedule {
+    type Attr = ScheduleAttr;
+    fn move_attrs(&self, succ: &Schedule) -> Vec<ScheduleAttr> {
+        let mut attrs = Vec::new();
+        for (&a, &b) in self.cards.iter().zip(succ.cards.iter()) {
+            if a != b {
+                attrs.push(ScheduleAttr::Card(a));
+                attrs.push(ScheduleAttr::Card(b));
+            }
+        }
+        for (&a, &b) in self.accs.iter().zip(succ.accs.iter()) {
+            if a != b {
+                attrs.push(ScheduleAttr::Acc(a));
+                attrs.push(ScheduleAttr::Acc(b));
+            }
+        }
+        attrs
+    }
+}
+
+// each strategy's cards, each strategy's accessories and the SP trio
+// are taken whole from one parent or the other.
+// anything that ends up on the team twice is swapped for something from the other parent,
+// or failing that, something random. a child that breaks the constraints is replaced by `self`.
+impl Crossover for Schedule {
+    fn crossover<R: Rng + ?Sized>(&self, other: &Schedule, glob: &PlayGlob, rng: &mut R) -> Schedule {
+        let mut child = *self;
+        for strat in 0 .. 3 {
+            let range = strat * 3 .. strat * 3 + 3;
+            if rng.gen_bool(0.5) {
+                child.cards[range.clone()].copy_from_slice(&other.cards[range.clone()]);
+            }
+            if rng.gen_bool(0.5) {
+                child.accs[range.clone()].copy_from_slice(&other.accs[range]);
+            }
+        }
+        if rng.gen_bool(0.5) {
+            child.sp3 = other.sp3;
+        }
+
+        let spares: Vec<usize> = self.cards.iter().chain(other.cards.iter()).copied().collect();
+        repair(&mut child.cards, &spares, glob.album.len(), rng);
+        if glob.inventory.len() >= 9 {
+            let spares: Vec<usize> = self.accs.iter().chain(other.accs.iter()).copied().collect();
+            repair(&mut child.accs, &spares, glob.inventory.len(), rng);
+        } else {
+            child.accs = self.accs;
+        }
+        // a mix of two teams that follow the constraints might not, e.g. if each parent
+        // has a required card in a different strategy and the child takes neither
+        if glob.constraints.allows(&child) {
+            child
+        } else {
+            *self
+        }
+    }
+}
+
+fn repair<R: Rng + ?Sized>(slots: &mut [usize; 9], spares: &[usize], size: usize, rng: &mut R) {
+    for i in 0 .. 9 {
+        if !slots[..i].contains(&slots[i]) {
+            continue;
+        }
+        slots[i] = match spares.iter().find(|v| !slots.contains(v)) {
+            Some(&v) => v,
+            None => {
+                let mut v = portable::below(rng, size);
+                while slots.contains(&v) {
+                    v = portable::below(rng, size);
+                }
+                v
+            },
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use local_search::PortableRng;
+    use crate::testing;
+
+    // the same on every platform (see local_search::portable); if these change, so does every seed anyone has written down
+    #[test]
+    fn random_teams_for_a_seed() {
+        let glob = testing::glob(120);
+        let mut rng = PortableRng::seed_from_u64(41);
+        assert_eq!(Schedule::new_random(&mut rng, &glob), Schedule {
+            cards: [7, 95, 57, 117, 90, 29, 19, 96, 67], sp3: [1, 0, 2], accs: [11, 12, 14, 1, 0, 3, 9, 7, 6],
+        });
+        assert_eq!(Schedule::new_random(&mut rng, &glob), Schedule {
+            cards: [68, 52, 12, 104, 3, 91, 115, 22, 2], sp3: [1, 0, 2], accs: [7, 11, 10, 8, 1, 5, 14, 3, 2],
+        });
+    }
+
+    #[test]
+    fn parse_move_weights() {
+        assert_eq!(MoveWeights::parse("back=1, formation = 2,card=0"), Some(MoveWeights {
+            green_swap: 1, card: 0, acc: 1, back_swap: 1, formation_swap: 2, strat_swap: 0, acc_swap: 0,
+        }));
+        assert_eq!(MoveWeights::parse("acc-swap=65535,strategy=3").map(|w| (w.acc_swap, w.strat_swap)), Some((65535, 3)));
+        for bad in ["", "card", "card=", "cards=1", "card=-1", "card=65536", "card=1,", "green=0,card=0,acc=0"] {
+            assert_eq!(MoveWeights::parse(bad), None, "{:?}", bad);
+        }
+        let climbing = MoveWeights::parse("card=0,acc=0,back=2").unwrap().with_defaults();
+        assert_eq!(climbing, MoveWeights { back_swap: 2, ..MoveWeights::default() });
+    }
+
+    fn swapped(cards: [usize; 9], a: usize, b: usize) -> Schedule {
+        let mut sched = Schedule { cards, accs: [0, 1, 2, 3, 4, 5, 6, 7, 8], sp3: [0, 1, 2] };
+        sched.cards.swap(a, b);
+        sched
+    }
+
+    // random_succ picks nth(below(rng, slots)), so with the default weights this numbering
+    // (green swaps, then cards, then accessories) is what keeps old seeds giving the same teams
+    #[test]
+    fn default_numbering() {
+        let it = ScheduleIterator::default();
+        let base = Schedule { cards: it.cards, accs: it.accs, sp3: it.sp3 };
+        let mut expected = Vec::new();
+        for i in 0 .. 18 {
+            expected.push(swapped(it.cards, i / 6, 3 + i % 6));
+        }
+        for slot in 0 .. 9 {
+            for card_i in 9 .. 50 {
+                let mut sched = base;
+                sched.cards[slot] = card_i;
+                expected.push(sched);
+            }
+        }
+        for slot in 0 .. 9 {
+            for acc_i in 9 .. 20 {
+                let mut sched = base;
+                sched.accs[slot] = acc_i;
+                expected.push(sched);
+            }
+        }
+        assert_eq!(it.size_hint(), (0, Some(expected.len())));
+        assert_eq!(it.clone().collect::<Vec<_>>(), expected);
+        for (n, sched) in expected.iter().enumerate() {
+            assert_eq!(it.clone().nth(n).as_ref(), Some(sched));
+        }
+        assert_eq!(it.clone().nth(expected.len()), None);
+    }
+
+    #[test]
+    #[allow(clippy::iter_nth_zero)] // nth is what's being tested, and it doesn't act like next
+    fn weighted_numbering() {
+        let weights = MoveWeights { green_swap: 2, card: 0, acc: 0, back_swap: 1, ..MoveWeights::default() };
+        let it = ScheduleIterator { weights, ..ScheduleIterator::default() };
+        let cards = it.cards;
+        assert_eq!(it.size_hint(), (0, Some(18 * 2 + 9)));
+        // each green swap takes up two slots
+        assert_eq!(it.clone().nth(0), Some(swapped(cards, 0, 3)));
+        assert_eq!(it.clone().nth(1), Some(swapped(cards, 0, 3)));
+        assert_eq!(it.clone().nth(2), Some(swapped(cards, 0, 4)));
+        assert_eq!(it.clone().nth(35), Some(swapped(cards, 2, 8)));
+        assert_eq!(it.clone().nth(36), Some(swapped(cards, 3, 6)));
+        assert_eq!(it.clone().nth(44), Some(swapped(cards, 5, 8)));
+        assert_eq!(it.clone().nth(45), None);
+        // nth carries on from the move after the slot it landed on
+        let mut stepped = it.clone();
+        assert_eq!(stepped.nth(1), Some(swapped(cards, 0, 3)));
+        assert_eq!(stepped.nth(0), Some(swapped(cards, 0, 4)));
+        assert_eq!(stepped.size_hint(), (0, Some(45 - 4)));
+        // next still goes through every move once
+        let all: Vec<Schedule> = it.collect();
+        assert_eq!(all.len(), 18 + 9);
+        assert_eq!(all[18], swapped(cards, 3, 6));
+    }
+
+    fn distinct(slots: &[usize]) -> bool {
+        slots.iter().enumerate().all(|(i, v)| !slots[.. i].contains(v))
+    }
+
+    #[test]
+    fn crossover_children_are_valid() {
+        let glob = testing::glob(20);
+        let mut rng = PortableRng::seed_from_u64(35);
+        for _ in 0 .. 200 {
+            let a = Schedule::new_random(&mut rng, &glob);
+            // the same cards and accessories in other strategies, so most children need repairing
+            let mut b = Schedule::new_random(&mut rng, &glob);
+            b.cards[.. 6].copy_from_slice(&a.cards[3 ..]);
+            b.accs[3 ..].copy_from_slice(&a.accs[.. 6]);
+            for child in [a.crossover(&b, &glob, &mut rng), b.crossover(&a, &glob, &mut rng)] {
+                assert!(distinct(&child.cards) && distinct(&child.accs) && distinct(&child.sp3), "{:?}", child);
+                assert!(child.cards.iter().all(|&card_i| card_i < glob.album.len()));
+                assert!(child.accs.iter().all(|&acc_i| acc_i < glob.inventory.len()));
+                assert!(child.sp3.iter().all(|&i| i < 9));
+                assert!(glob.constraints.allows(&child));
+            }
+        }
+    }
+
+    #[test]
+    fn genetic_best_never_gets_worse() {
+        use local_search::{SimpleIterSolver, ReusableBuffer};
+        use local_search::genetic::{Genetic, Params};
+        let glob = testing::glob(20);
+        let mut rng = PortableRng::seed_from_u64(35);
+        let seeds: Vec<Schedule> = (0 .. 7).map(|_| Schedule::new_random(&mut rng, &glob)).collect();
+        let s0 = Schedule::new_random(&mut rng, &glob);
+        let start = s0.energy(&glob, &mut Status::create());
+        let pm = Params { rng, size: 8, seeds, elite: 1, tournament: 2, mutation: 0.5 };
+        let mut ga = Genetic::org(s0, glob.clone(), pm);
+        let mut best = start;
+        for _ in 0 .. 30 {
+            let (sched, energy) = ga.advance().unwrap();
+            assert!(energy <= best, "{} after {}", energy, best);
+            assert!(distinct(&sched.cards) && distinct(&sched.accs));
+            best = energy;
+        }
+        assert!(best < start);
     }
 }
@@ -0,0 +1,93 @@
+use super::PlayGlob;
+use super::card::Card;
+use super::schedule::Schedule;
+use card_consumer::json_card::TrimCard;
+
+// what a card you don't have yet would do for your teams
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prospect {
+    pub ordinal: u32,
+    pub gains: Vec<f64>, // voltage, one per song, after re-optimizing the team around the new card
+    pub teams: Vec<Schedule>, // the re-optimized teams; same order
+}
+
+impl Prospect {
+    pub fn total(&self) -> f64 {
+        self.gains.iter().sum()
+    }
+}
+
+// `floors` are a song and the best team found for it, each scored on its own.
+// every team is hill climbed first (see local_search::hill_climb and PlayGlob::for_climbing) so that gains aren't just
+// improvements that were available all along, then each card in `candidates` is added to the album
+// at `lb` with no skill tree and the teams are hill climbed again.
+// cards already in the album are left out.
+// returns a prospect for every other card, best total gain first.
+pub fn scout(floors: &[(PlayGlob, Schedule)], candidates: &[&TrimCard], lb: u8) -> Vec<Prospect> {
+    let floors: Vec<(PlayGlob, Schedule)> = floors.iter().map(|(glob, team)| (glob.for_climbing(), *team)).collect();
+    let bases: Vec<(Schedule, f64)> = floors.iter()
+        .map(|(glob, team)| {
+            let (base, base_energy, _) = local_search::hill_climb(*team, glob);
+            (base, base_energy)
+        })
+        .collect();
+
+    let mut prospects = Vec::new();
+    for jc in candidates.iter() {
+        if floors.iter().any(|(glob, _)| glob.album.iter().any(|card| card.ordinal == jc.ordinal)) {
+            continue;
+        }
+        let card = Card::instantiate_json(jc, lb, false);
+        let mut gains = Vec::with_capacity(floors.len());
+        let mut teams = Vec::with_capacity(floors.len());
+        for ((glob, _), &(base, base_energy)) in floors.iter().zip(bases.iter()) {
+            let scouted = glob.with_card(card.clone());
+            let (team, energy, _) = local_search::hill_climb(base, &scouted);
+            gains.push((base_energy - energy).max(0.0));
+            teams.push(team);
+        }
+        prospects.push(Prospect { ordinal: jc.ordinal, gains, teams });
+    }
+    prospects.sort_by(|a, b| b.total().partial_cmp(&a.total()).unwrap());
+    prospects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use local_search::{PortableRng, SearchState, ReusableBuffer};
+    use crate::live_show::Status;
+    use crate::testing;
+
+    #[test]
+    fn scouts_cards_not_in_the_album() {
+        let glob = testing::glob(120);
+        let mut rng = PortableRng::seed_from_u64(46);
+        let team = Schedule::new_random(&mut rng, &glob);
+        let owned = |jc: &&TrimCard| glob.album.iter().any(|card| card.ordinal == jc.ordinal);
+        let have: Vec<&TrimCard> = testing::cards().values().filter(owned).collect();
+        // two cards that would make the team, and one that wouldn't
+        let new: Vec<&TrimCard> = [12, 16, 375].iter().map(|ordinal| &testing::cards()[ordinal]).collect();
+        assert!(!new.iter().any(owned));
+        let candidates = [have[0], new[0], have[1], new[1], new[2]];
+        let prospects = scout(&[(glob.clone(), team)], &candidates, 5);
+
+        let ordinals: Vec<u32> = prospects.iter().map(|p| p.ordinal).collect();
+        assert_eq!(ordinals, [375, 12, 16]);
+        assert!(prospects[0].total() > prospects[1].total() && prospects[1].total() > 0.0);
+        assert_eq!(prospects[2].total(), 0.0);
+
+        let climbing = glob.for_climbing();
+        let (_, base_energy, _) = local_search::hill_climb(team, &climbing);
+        for p in prospects.iter() {
+            // the scouted card is the last one in the album, and a team can only gain by using it
+            let jc = candidates.iter().find(|jc| jc.ordinal == p.ordinal).unwrap();
+            let scouted = climbing.with_card(Card::instantiate_json(jc, 5, false));
+            let energy = p.teams[0].energy(&scouted, &mut Status::create());
+            assert_eq!(p.gains[0], base_energy - energy);
+            assert_eq!(p.gains[0] > 0.0, p.teams[0].cards.contains(&glob.album.len()));
+        }
+        assert_eq!(scout(&[(glob, team)], &candidates, 5), prospects);
+    }
+}
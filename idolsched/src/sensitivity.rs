@@ -0,0 +1,88 @@
+use local_search::ReusableBuffer;
+use super::PlayGlob;
+use super::schedule::Schedule;
+use super::live_show::Status;
+
+// the best and worst thing that could take a slot's place, as (album or inventory index, change in voltage).
+// None if nothing can, e.g. because the slot is locked.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct SlotReport {
+    pub best: Option<(usize, f64)>,
+    pub worst: Option<(usize, f64)>,
+}
+
+impl SlotReport {
+    fn add(&mut self, index: usize, delta: f64) {
+        if !matches!(self.best, Some((_, best)) if best >= delta) {
+            self.best = Some((index, delta));
+        }
+        if !matches!(self.worst, Some((_, worst)) if worst <= delta) {
+            self.worst = Some((index, delta));
+        }
+    }
+}
+
+// how much a team leans on each of its cards and accessories.
+// a slot whose best replacement loses next to nothing is one you could give up
+// (say, to lend the card to another team); one whose best replacement loses a lot is critical.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sensitivity {
+    pub voltage: f64,
+    pub cards: [SlotReport; 9], // GGGBBBRRR
+    pub accs: [SlotReport; 9],
+}
+
+// tries every card in the album and every accessory in the inventory that isn't on the team
+// in each slot, leaving out replacements the constraints don't allow.
+// this doesn't go by glob.moves, so it covers every slot even if the search never changed cards or accessories.
+pub fn sensitivity(glob: &PlayGlob, sched: &Schedule) -> Sensitivity {
+    let mut buf = Status::create();
+    let voltage = glob.est_voltage(sched, &mut buf);
+    let mut report = Sensitivity { voltage, cards: Default::default(), accs: Default::default() };
+    let try_succ = |succ: &Schedule, buf: &mut Status| {
+        if !glob.constraints.allows(succ) {
+            return None;
+        }
+        buf.refresh();
+        Some(glob.est_voltage(succ, buf) - voltage)
+    };
+    for slot in 0 .. 9 {
+        for card_i in (0 .. glob.album.len()).filter(|card_i| !sched.cards.contains(card_i)) {
+            let mut succ = *sched;
+            succ.cards[slot] = card_i;
+            if let Some(delta) = try_succ(&succ, &mut buf) {
+                report.cards[slot].add(card_i, delta);
+            }
+        }
+    }
+    for slot in 0 .. 9 {
+        for acc_i in (0 .. glob.inventory.len()).filter(|acc_i| !sched.accs.contains(acc_i)) {
+            let mut succ = *sched;
+            succ.accs[slot] = acc_i;
+            if let Some(delta) = try_succ(&succ, &mut buf) {
+                report.accs[slot].add(acc_i, delta);
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use local_search::PortableRng;
+    use crate::testing;
+    use crate::schedule::MoveWeights;
+
+    // the search's move weights don't limit what gets tried
+    #[test]
+    fn every_slot_whatever_the_moves() {
+        let glob = testing::glob(120);
+        let sched = Schedule::new_random(&mut PortableRng::seed_from_u64(41), &glob);
+        let report = sensitivity(&glob, &sched);
+        assert!(report.cards.iter().chain(report.accs.iter()).all(|slot| slot.best.is_some()));
+        let swaps_only = PlayGlob { moves: MoveWeights::parse("card=0,acc=0,back=1").unwrap(), ..glob.clone() };
+        assert_eq!(sensitivity(&swaps_only, &sched), report);
+    }
+}
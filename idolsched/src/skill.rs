@@ -156,6 +156,16 @@ impl Skill {
         }
         Skill { target_mask, .. *self }
     }
+
+    // imbue_pos for a guest, who isn't on the team:
+    // skills for the guest itself or its strategy reach nobody, and ones for everyone else reach the whole team
+    pub fn imbue_guest(&self) -> Skill {
+        let mut target_mask = self.target_mask;
+        if self.others {
+            target_mask |= 0x1ff;
+        }
+        Skill { target_mask, .. *self }
+    }
 }
 
 pub fn mask_for(jc: &TrimCard) -> u64 {
@@ -328,6 +338,38 @@ fn subunit_mask_m(member: Idol) -> u64 {
     }
 }
 
+const MUSE: u64       = 0x1ff << 32;
+const AQOURS: u64     = 0x1ff << (32 + 9);
+const NIJIGASAKI: u64 = 0x3ff << (32 + 18);
+
+// the cards a group name covers, as a mask to test against Card::skill_mask.
+// names are lowercase with no punctuation, e.g. "muse", "guiltykiss", "smile".
+pub fn group_mask(name: &str) -> Option<u64> {
+    use Attribute::*;
+    let mask = match name {
+        "muse"       => MUSE,
+        "aqours"     => AQOURS,
+        "nijigasaki" => NIJIGASAKI,
+        "printemps"  => PRINTEMPS,
+        "bibi"       => BIBI,
+        "lilywhite"  => LILY_WHITE,
+        "cyaron"     => CYARON,
+        "guiltykiss" => GUILTY_KISS,
+        "azalea"     => AZALEA,
+        "azuna"      => AZUNA,
+        "qu4rtz"     => QU4RTZ,
+        "diverdiva"  => DIVER_DIVA,
+        "smile"      => attribute_mask(Smile),
+        "pure"       => attribute_mask(Pure),
+        "cool"       => attribute_mask(Cool),
+        "active"     => attribute_mask(Active),
+        "natural"    => attribute_mask(Natural),
+        "elegant"    => attribute_mask(Elegant),
+        _ => return None,
+    };
+    Some(mask)
+}
+
 fn member_ofs(member: Idol) -> u8 {
     use Idol::*;
     match member {
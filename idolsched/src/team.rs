@@ -0,0 +1,289 @@
+use std::fmt::{self, Formatter, Display};
+use serde::{Deserialize, Serialize};
+use sifas_data::prelude::*;
+use super::PlayGlob;
+use super::schedule::Schedule;
+use super::acct_info::{AccInfo, AccKind};
+
+use TeamError::*;
+
+// a team as a person would describe it, rather than in terms of album indexes,
+// so that it means the same thing to any account that has the right cards and accessories.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct TeamSpec {
+    pub cards: [u32; 9], // card ordinals; GGGBBBRRR
+    pub accs: [AccRef; 9], // GGGBBBRRR
+    #[serde(default = "default_sp3")]
+    pub sp3: [usize; 3], // indexes into `cards`; center, left, right
+}
+
+// an accessory can be given either as an index into one particular account's
+// accessory list (handy when writing a team by hand), or as the accessory itself.
+// teams made by the program always use the latter.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum AccRef {
+    Index(usize),
+    Item(AccInfo),
+}
+
+fn default_sp3() -> [usize; 3] {
+    [1, 0, 2]
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TeamError {
+    CardNotOwned(u32),
+    DuplicateCard(u32),
+    NoSuchAcc(usize),
+    DuplicateAcc(usize),
+    AccNotOwned(AccInfo),
+    BadSpSlot(usize),
+    DuplicateSpSlot(usize),
+    BadJson(String),
+    BadCode,
+}
+
+impl Display for TeamError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            CardNotOwned(ord) => write!(f, "card {} is not in the album", ord),
+            DuplicateCard(ord) => write!(f, "card {} is on the team more than once", ord),
+            NoSuchAcc(i) => write!(f, "there is no accessory #{}", i),
+            DuplicateAcc(i) => write!(f, "accessory #{} is on the team more than once", i),
+            AccNotOwned(info) => write!(f,
+                "not enough {:?} {:?} {:?} (LB{} Lv{} SL{}) in the accessory list",
+                info.rarity, info.attribute, info.kind, info.lb, info.lv, info.sl
+            ),
+            BadSpSlot(i) => write!(f, "SP slot {} is not a team position (0-8)", i),
+            DuplicateSpSlot(i) => write!(f, "position {} is used for SP more than once", i),
+            BadJson(e) => write!(f, "couldn't parse team json: {}", e),
+            BadCode => write!(f, "not a valid team code"),
+        }
+    }
+}
+
+impl std::error::Error for TeamError {}
+
+impl TeamSpec {
+    pub fn from_schedule(sched: &Schedule, glob: &PlayGlob) -> TeamSpec {
+        let mut cards = [0; 9];
+        for (i, &card_i) in sched.cards.iter().enumerate() {
+            cards[i] = glob.album[card_i].ordinal;
+        }
+        let mut accs = [AccRef::Index(0); 9];
+        for (i, &acc_i) in sched.accs.iter().enumerate() {
+            accs[i] = AccRef::Item(glob.inventory[acc_i].info);
+        }
+        TeamSpec { cards, accs, sp3: sched.sp3 }
+    }
+
+    pub fn to_schedule(&self, glob: &PlayGlob) -> Result<Schedule, TeamError> {
+        let mut cards = [0; 9];
+        for (i, &ordinal) in self.cards.iter().enumerate() {
+            if self.cards[..i].contains(&ordinal) {
+                return Err(DuplicateCard(ordinal));
+            }
+            cards[i] = glob.album.iter()
+                .position(|card| card.ordinal == ordinal)
+                .ok_or(CardNotOwned(ordinal))?;
+        }
+
+        // indexes are placed first, so that items can't claim an accessory
+        // that the team also asks for by index
+        let mut accs = [0; 9];
+        let mut used = vec![false; glob.inventory.len()];
+        for (i, acc) in self.accs.iter().enumerate() {
+            if let AccRef::Index(acc_i) = *acc {
+                if acc_i >= glob.inventory.len() {
+                    return Err(NoSuchAcc(acc_i));
+                }
+                if used[acc_i] {
+                    return Err(DuplicateAcc(acc_i));
+                }
+                used[acc_i] = true;
+                accs[i] = acc_i;
+            }
+        }
+        for (i, acc) in self.accs.iter().enumerate() {
+            if let AccRef::Item(info) = *acc {
+                let acc_i = glob.inventory.iter().enumerate()
+                    .position(|(j, inv_acc)| !used[j] && inv_acc.info == info)
+                    .ok_or(AccNotOwned(info))?;
+                used[acc_i] = true;
+                accs[i] = acc_i;
+            }
+        }
+
+        for (i, &pos) in self.sp3.iter().enumerate() {
+            if pos >= 9 {
+                return Err(BadSpSlot(pos));
+            }
+            if self.sp3[..i].contains(&pos) {
+                return Err(DuplicateSpSlot(pos));
+            }
+        }
+
+        Ok(Schedule { cards, accs, sp3: self.sp3 })
+    }
+
+    // accepts either json or a team code
+    pub fn parse(text: &str) -> Result<TeamSpec, TeamError> {
+        let text = text.trim();
+        if text.starts_with('{') {
+            serde_json::from_str(text).map_err(|e| BadJson(e.to_string()))
+        } else {
+            TeamSpec::from_code(text)
+        }
+    }
+
+    // team codes are url-safe base64 of:
+    //   1 byte version,
+    //   9 * 2 bytes card ordinals (big endian),
+    //   2 bytes sp3 (4 bits each, center in the low bits),
+    //   9 * 4 bytes accessories.
+    // an accessory is kind << 4 | attribute, rarity << 4 | lb, lv, sl,
+    // or 0xf0, 0, index (big endian) for an AccRef::Index.
+    pub fn to_code(&self) -> String {
+        let mut bytes = Vec::with_capacity(CODE_LEN);
+        bytes.push(CODE_VERSION);
+        for &ordinal in self.cards.iter() {
+            bytes.extend_from_slice(&(ordinal as u16).to_be_bytes());
+        }
+        let sp3 = self.sp3[0] | self.sp3[1] << 4 | self.sp3[2] << 8;
+        bytes.extend_from_slice(&(sp3 as u16).to_be_bytes());
+        for acc in self.accs.iter() {
+            match *acc {
+                AccRef::Item(info) => bytes.extend_from_slice(&[
+                    kind_code(info.kind) << 4 | info.attribute as u8,
+                    rarity_code(info.rarity) << 4 | info.lb,
+                    info.lv,
+                    info.sl,
+                ]),
+                AccRef::Index(i) => {
+                    bytes.extend_from_slice(&[0xf0, 0]);
+                    bytes.extend_from_slice(&(i as u16).to_be_bytes());
+                },
+            }
+        }
+        base64::encode_config(&bytes, base64::URL_SAFE_NO_PAD)
+    }
+
+    pub fn from_code(code: &str) -> Result<TeamSpec, TeamError> {
+        let bytes = base64::decode_config(code, base64::URL_SAFE_NO_PAD).map_err(|_| BadCode)?;
+        if bytes.len() != CODE_LEN || bytes[0] != CODE_VERSION {
+            return Err(BadCode);
+        }
+
+        let mut cards = [0; 9];
+        for (i, pair) in bytes[1 .. 19].chunks(2).enumerate() {
+            cards[i] = u16::from_be_bytes([pair[0], pair[1]]) as u32;
+        }
+
+        let sp3_bits = u16::from_be_bytes([bytes[19], bytes[20]]) as usize;
+        let sp3 = [sp3_bits & 0xf, sp3_bits >> 4 & 0xf, sp3_bits >> 8 & 0xf];
+
+        let mut accs = [AccRef::Index(0); 9];
+        for (i, quad) in bytes[21 ..].chunks(4).enumerate() {
+            accs[i] = if quad[0] == 0xf0 {
+                AccRef::Index(u16::from_be_bytes([quad[2], quad[3]]) as usize)
+            } else {
+                AccRef::Item(AccInfo {
+                    kind: kind_from_code(quad[0] >> 4).ok_or(BadCode)?,
+                    attribute: attribute_from_code(quad[0] & 0xf).ok_or(BadCode)?,
+                    rarity: rarity_from_code(quad[1] >> 4).ok_or(BadCode)?,
+                    lb: quad[1] & 0xf,
+                    lv: quad[2],
+                    sl: quad[3],
+                })
+            };
+        }
+
+        Ok(TeamSpec { cards, accs, sp3 })
+    }
+}
+
+const CODE_VERSION: u8 = 1;
+const CODE_LEN: usize = 1 + 9 * 2 + 2 + 9 * 4;
+
+const ACC_KINDS: [AccKind; 14] = {
+    use AccKind::*;
+    [
+        Empty,
+        Brooch, Keychain,
+        Bracelet, Hairpin,
+        Necklace, Earring,
+        Pouch, Ribbon,
+        Wristband, Towel,
+        Bangle, Choker, Belt,
+    ]
+};
+
+fn kind_code(kind: AccKind) -> u8 {
+    ACC_KINDS.iter().position(|&k| k == kind).unwrap() as u8
+}
+
+fn kind_from_code(code: u8) -> Option<AccKind> {
+    ACC_KINDS.get(code as usize).copied()
+}
+
+fn attribute_from_code(code: u8) -> Option<Attribute> {
+    use Attribute::*;
+    [Smile, Pure, Cool, Active, Natural, Elegant, Neutral].iter()
+        .find(|&&att| att as u8 == code)
+        .copied()
+}
+
+fn rarity_code(rarity: Rarity) -> u8 {
+    match rarity {
+        Rarity::R => 0,
+        Rarity::Sr => 1,
+        Rarity::Ur => 2,
+    }
+}
+
+fn rarity_from_code(code: u8) -> Option<Rarity> {
+    match code {
+        0 => Some(Rarity::R),
+        1 => Some(Rarity::Sr),
+        2 => Some(Rarity::Ur),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_round_trip() {
+        let item = |kind, attribute, rarity, lb, lv, sl| AccRef::Item(AccInfo { attribute, kind, rarity, lb, lv, sl });
+        let spec = TeamSpec {
+            cards: [1, 2, 3, 400, 500, 600, 7000, 8000, 65535],
+            accs: [
+                item(AccKind::Brooch, Attribute::Smile, Rarity::Ur, 5, 80, 15),
+                item(AccKind::Belt, Attribute::Elegant, Rarity::Sr, 0, 1, 1),
+                item(AccKind::Necklace, Attribute::Neutral, Rarity::R, 3, 30, 4),
+                AccRef::Index(0),
+                AccRef::Index(7),
+                AccRef::Index(65535),
+                item(AccKind::Choker, Attribute::Natural, Rarity::Ur, 5, 90, 15),
+                item(AccKind::Towel, Attribute::Pure, Rarity::Ur, 2, 60, 9),
+                AccRef::Index(300),
+            ],
+            sp3: [4, 8, 0],
+        };
+        let code = spec.to_code();
+        assert_eq!(code.len(), 76);
+        assert_eq!(TeamSpec::from_code(&code), Ok(spec));
+        assert_eq!(TeamSpec::parse(&format!(" {}\n", code)), Ok(spec));
+    }
+
+    #[test]
+    fn bad_codes() {
+        let code = TeamSpec { cards: [1; 9], accs: [AccRef::Index(0); 9], sp3: default_sp3() }.to_code();
+        assert_eq!(TeamSpec::from_code(&code[1 ..]), Err(BadCode));
+        assert_eq!(TeamSpec::from_code(&format!("{}AA", code)), Err(BadCode));
+        assert_eq!(TeamSpec::from_code("not a code"), Err(BadCode));
+    }
+}
@@ -0,0 +1,29 @@
+// things for tests to work on: the account ui_cli comes with and the cards ui_web ships with,
+// on made-up songs of a steady note every half second
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use card_consumer::json_card::TrimCard;
+use super::PlayGlob;
+use super::acct_info::AcctInfo;
+
+pub fn cards() -> &'static BTreeMap<u32, TrimCard> {
+    static CARDS: OnceLock<BTreeMap<u32, TrimCard>> = OnceLock::new();
+    CARDS.get_or_init(|| serde_json::from_str(include_str!("../../ui_web/js/cards.json")).unwrap())
+}
+
+pub fn song_json(notes: usize) -> String {
+    let notes: Vec<String> = (0 .. notes).map(|i| format!("{{\"time\": {}, \"gimmick\": null}}", i * 500)).collect();
+    format!(
+        "{{\"display_order\": 1, \"song_name\": \"test\", \"song_attribute\": 4, \"song_difficulty\": 30, \
+        \"ranks\": {{\"S\": 1000000, \"A\": 1, \"B\": 1, \"C\": 1}}, \"note_damage\": 300, \"notes\": [{}]}}",
+        notes.join(", ")
+    )
+}
+
+pub fn acct() -> AcctInfo {
+    super::init_acct(include_str!("../../ui_cli/account.json")).unwrap()
+}
+
+pub fn glob(notes: usize) -> PlayGlob {
+    super::init_glob(cards(), &acct(), 10015301, &song_json(notes), None).unwrap()
+}
@@ -1,73 +1,272 @@
-use crate::{SearchState, ReusableBuffer, SimpleIterSolver};
+use crate::{SearchState, ReusableBuffer, SimpleIterSolver, portable};
+use crate::memo::{Evaluator, MemoStats};
 use rand::Rng;
-use rand::seq::SliceRandom;
 
 pub struct Params<R: Rng> {
     pub rng: R,
     pub t0: f64,
-    pub alpha: f64,
+    pub cooling: Cooling,
+}
+
+// how the temperature changes from one step to the next
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Cooling {
+    // multiply by alpha every step
+    Geometric(f64),
+    // straight down from t0 to 0 over `steps` steps
+    Linear { steps: u64 },
+    // geometric, with alpha chosen to reach `t_end` after `steps` steps
+    ToTarget { t_end: f64, steps: u64 },
+    // geometric, but back up to t0 every `period` steps (never, if it is 0)
+    Reheat { alpha: f64, period: u64 },
+    // modified Lam: nudge the temperature up or down so that the acceptance rate
+    // follows a curve that starts near 1, holds at 0.44, and falls to nearly 0 by `steps`.
+    // t0 only matters for the first few hundred steps.
+    Lam { steps: u64 },
+}
+
+// the cooling schedules the UIs offer by name (see Cooling for what each does),
+// for runs of a known number of steps
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum CoolingKind {
+    #[default]
+    Geometric,
+    Linear,
+    Target,
+    Reheat,
+    Lam,
+}
+
+impl CoolingKind {
+    // geometric, linear, target, reheat or lam
+    pub fn parse(name: &str) -> Option<CoolingKind> {
+        match name {
+            "geometric" => Some(CoolingKind::Geometric),
+            "linear" => Some(CoolingKind::Linear),
+            "target" => Some(CoolingKind::Target),
+            "reheat" => Some(CoolingKind::Reheat),
+            "lam" => Some(CoolingKind::Lam),
+            _ => None,
+        }
+    }
+
+    // the schedule for a run of `steps` steps from `t0`. Geometric cools at ALPHA whatever the length
+    pub fn cooling(self, steps: u64, t0: f64) -> Cooling {
+        match self {
+            CoolingKind::Geometric => Cooling::Geometric(ALPHA),
+            CoolingKind::Linear => Cooling::Linear { steps },
+            CoolingKind::Target => Cooling::ToTarget { t_end: T_END.min(t0), steps },
+            CoolingKind::Reheat => {
+                let period = (steps / REHEATS).max(1);
+                let alpha = (T_END.min(t0) / t0).powf(1.0 / period as f64);
+                Cooling::Reheat { alpha, period }
+            },
+            CoolingKind::Lam => Cooling::Lam { steps },
+        }
+    }
+}
+
+// starting temperatures. REFINE_T0 is low enough that a good team
+// will mostly stay put, so it's for polishing rather than exploring.
+pub const T0: f64 = 10_000.0;
+pub const REFINE_T0: f64 = 100.0;
+pub const ALPHA: f64 = 1.0 - (1.0/65_536.0);
+// where the schedules that aim for a final temperature end up
+pub const T_END: f64 = 10.0;
+// times reheat cooling goes back up to t0 over the run
+pub const REHEATS: u64 = 4;
+// start_temp's `auto` picks a temperature at which an average uphill move
+// is taken with this probability (or REFINE_ACCEPT, when refining)
+pub const AUTO_ACCEPT: f64 = 0.8;
+pub const REFINE_ACCEPT: f64 = 0.05;
+pub const CALIBRATION_SAMPLES: usize = 200;
+
+// weight given to each new step in the running acceptance rate
+const ACCEPT_DECAY: f64 = 1.0 / 500.0;
+// how much Lam cooling changes the temperature in one step
+const LAM_NUDGE: f64 = 0.999;
+
+// target acceptance rate for Lam cooling, `f` of the way through the run
+fn lam_target(f: f64) -> f64 {
+    if f < 0.15 {
+        0.44 + 0.56 * 560f64.powf(-f / 0.15)
+    } else if f < 0.65 {
+        0.44
+    } else {
+        0.44 * 440f64.powf(-(f - 0.65) / 0.35)
+    }
+}
+
+// the temperature at which a move that costs `delta` is taken with probability `accept`
+pub fn temp_for(delta: f64, accept: f64) -> f64 {
+    -delta / accept.ln()
+}
+
+// takes a random walk of `samples` steps from `origin`, and returns
+// the average energy cost of the uphill moves it saw along the way.
+// pass this to temp_for to choose a starting temperature that suits the problem's scale;
+// None if the walk never went uphill.
+pub fn calibrate<St: SearchState, R: Rng>(rng: &mut R, origin: &St, glob: &St::Glob, samples: usize)
+-> Option<f64> {
+    let mut buf = St::Buf::create();
+    let mut here = origin.clone();
+    let mut here_e = here.energy(glob, &mut buf);
+    let mut total = 0.0;
+    let mut uphill = 0;
+    for _ in 0 .. samples {
+        let t = random_succ(rng, &here, glob);
+        buf.refresh();
+        let te = t.energy(glob, &mut buf);
+        if te > here_e {
+            total += te - here_e;
+            uphill += 1;
+        }
+        here = t;
+        here_e = te;
+    }
+    if uphill == 0 {
+        None
+    } else {
+        Some(total / uphill as f64)
+    }
+}
+
+// T0 or (when `refine`-ing a given start) REFINE_T0, unless `auto` says to work it out
+// from the problem at hand with calibrate. the walk draws from `rng`.
+pub fn start_temp<St: SearchState, R: Rng>(rng: &mut R, s0: &St, glob: &St::Glob, auto: bool, refine: bool) -> f64 {
+    let fixed = if refine { REFINE_T0 } else { T0 };
+    if !auto {
+        return fixed;
+    }
+    let accept = if refine { REFINE_ACCEPT } else { AUTO_ACCEPT };
+    match calibrate(rng, s0, glob, CALIBRATION_SAMPLES) {
+        Some(delta) => temp_for(delta, accept),
+        None => fixed,
+    }
 }
 
 pub struct Annealer<St: SearchState, R: Rng> {
     glob: St::Glob,
-    buf: St::Buf,
+    eval: Evaluator<St>,
     rng: R,
+    t0: f64,
     temp: f64,
-    alpha: f64,
+    cooling: Cooling,
+    step: u64,
+    accept_rate: f64,
     last: (St, f64),
 }
 
+impl<St: SearchState, R: Rng> Annealer<St, R> {
+    fn cool(&mut self) {
+        use Cooling::*;
+        self.step += 1;
+        self.temp = match self.cooling {
+            Geometric(alpha) => self.temp * alpha,
+            Linear { steps } => self.t0 * (1.0 - self.step as f64 / steps.max(1) as f64).max(0.0),
+            ToTarget { t_end, steps } => self.t0 * (t_end / self.t0).powf(self.step as f64 / steps.max(1) as f64),
+            Reheat { alpha, period } => {
+                if self.step.checked_rem(period) == Some(0) {
+                    self.t0
+                } else {
+                    self.temp * alpha
+                }
+            },
+            Lam { steps } => {
+                let target = lam_target(self.step as f64 / steps.max(1) as f64);
+                if self.accept_rate > target {
+                    self.temp * LAM_NUDGE
+                } else {
+                    self.temp / LAM_NUDGE
+                }
+            },
+        };
+    }
+}
+
 impl<St: SearchState, R: Rng> SimpleIterSolver<St> for Annealer<St, R> {
     type Pm = Params<R>;
     fn org(origin: St, glob: St::Glob, params: Params<R>) -> Annealer<St, R> {
-        let mut buf = St::Buf::create();
-        let org_energy = origin.energy(&glob, &mut buf);
+        let mut eval = Evaluator::new();
+        let org_energy = eval.energy(&origin, &glob);
         Annealer {
             glob,
-            buf,
+            eval,
             rng: params.rng,
+            t0: params.t0,
             temp: params.t0,
-            alpha: params.alpha,
+            cooling: params.cooling,
+            step: 0,
+            accept_rate: 0.5,
             last: (origin, org_energy)
         }
     }
 
+    fn set_memo(&mut self, capacity: usize) {
+        self.eval.set_memo(capacity);
+    }
+
+    fn memo_stats(&self) -> Option<MemoStats> {
+        self.eval.memo_stats()
+    }
+
     fn advance(&mut self) -> Option<(St, f64)> {
         let t = random_succ(&mut self.rng, &self.last.0, &self.glob);
-        self.buf.refresh();
-        let te = t.energy(&self.glob, &mut self.buf);
+        let te = self.eval.energy(&t, &self.glob);
 
         let pt = p(self.last.1, te, self.temp).min(1.0);
 
-        if self.rng.gen_bool(pt) {
+        let accepted = self.rng.gen_bool(pt);
+        if accepted {
             self.last = (t, te);
         }
+        let hit = if accepted { 1.0 } else { 0.0 };
+        self.accept_rate += (hit - self.accept_rate) * ACCEPT_DECAY;
 
-        self.temp *= self.alpha;
+        self.cool();
 
         Some((self.last.0.clone(), self.last.1))
     }
+
+    fn temperature(&self) -> Option<f64> {
+        Some(self.temp)
+    }
+
+    // running average of how often moves are taken
+    fn accept_rate(&self) -> Option<f64> {
+        Some(self.accept_rate)
+    }
 }
 
-fn random_succ<St: SearchState, R: Rng>(rng: &mut R, s: &St, glob: &St::Glob) -> St {
+// panics if `s` has no successors at all, rather than looking for one forever
+pub(crate) fn random_succ<St: SearchState, R: Rng>(rng: &mut R, s: &St, glob: &St::Glob) -> St {
+    const NO_SUCCESSORS: &str = "random_succ: the state has no successors";
     let mut it = s.successors(glob);
     if let (_, Some(upper_bound)) = it.size_hint() {
+        assert!(upper_bound > 0, "{}", NO_SUCCESSORS);
+        let mut misses = 0;
         loop {
-            let n: usize = rng.gen_range(0, upper_bound);
-            if let Some(t) = it.skip(n).next() {
+            let n = portable::below(rng, upper_bound);
+            if let Some(t) = it.nth(n) {
                 return t;
             }
+            // every slot might be one nth turns down, so once in a while make sure one isn't
+            misses += 1;
+            if misses == 1024 {
+                assert!(s.successors(glob).next().is_some(), "{}", NO_SUCCESSORS);
+                misses = 0;
+            }
             it = s.successors(glob);
         }
     } else {
         let mut succs: Vec<St> = it.collect();
-        succs.shuffle(rng);
+        portable::shuffle(rng, &mut succs);
         succs.truncate(1);
-        succs.pop().unwrap()
+        succs.pop().expect(NO_SUCCESSORS)
     }
 }
 
-fn p(se: f64, te: f64, temp: f64) -> f64 {
+pub(crate) fn p(se: f64, te: f64, temp: f64) -> f64 {
     if te < se {
         1.0
     } else {
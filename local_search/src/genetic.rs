@@ -0,0 +1,109 @@
+use crate::{Crossover, SimpleIterSolver, portable};
+use crate::memo::{Evaluator, MemoStats};
+use crate::anneal::random_succ;
+use rand::Rng;
+
+// a plain generational genetic algorithm.
+// each generation keeps the `elite` best individuals as they are,
+// and fills the rest of the population with children of parents picked by tournament,
+// each child getting one random move with probability `mutation`.
+pub struct Params<St, R: Rng> {
+    pub rng: R,
+    pub size: usize, // population size
+    pub seeds: Vec<St>, // starting individuals besides the origin
+    pub elite: usize,
+    pub tournament: usize, // individuals looked at to pick each parent
+    pub mutation: f64,
+}
+
+// if there aren't enough seeds to fill the population,
+// the rest are made by taking this many random moves from the origin
+const WANDER: usize = 20;
+
+pub struct Genetic<St: Crossover, R: Rng> {
+    glob: St::Glob,
+    eval: Evaluator<St>,
+    rng: R,
+    elite: usize,
+    tournament: usize,
+    mutation: f64,
+    pop: Vec<(St, f64)>, // sorted best first
+}
+
+impl<St: Crossover, R: Rng> Genetic<St, R> {
+    fn evaluate(&mut self, st: St) -> (St, f64) {
+        let e = self.eval.energy(&st, &self.glob);
+        (st, e)
+    }
+
+    fn pick(&mut self) -> usize {
+        // the population is sorted, so the lowest index drawn wins
+        (0 .. self.tournament.max(1))
+            .map(|_| portable::below(&mut self.rng, self.pop.len()))
+            .min()
+            .unwrap()
+    }
+
+    fn sort(&mut self) {
+        self.pop.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+    }
+}
+
+impl<St: Crossover, R: Rng> SimpleIterSolver<St> for Genetic<St, R> {
+    type Pm = Params<St, R>;
+    fn org(origin: St, glob: St::Glob, params: Params<St, R>) -> Genetic<St, R> {
+        let size = params.size.max(2);
+        let mut ga = Genetic {
+            glob,
+            eval: Evaluator::new(),
+            rng: params.rng,
+            elite: params.elite.min(size - 1),
+            tournament: params.tournament,
+            mutation: params.mutation,
+            pop: Vec::with_capacity(size),
+        };
+
+        let mut starts = vec![origin.clone()];
+        starts.extend(params.seeds.into_iter().take(size - 1));
+        while starts.len() < size {
+            let mut st = origin.clone();
+            for _ in 0 .. WANDER {
+                st = random_succ(&mut ga.rng, &st, &ga.glob);
+            }
+            starts.push(st);
+        }
+        for st in starts {
+            let ind = ga.evaluate(st);
+            ga.pop.push(ind);
+        }
+        ga.sort();
+        ga
+    }
+
+    fn set_memo(&mut self, capacity: usize) {
+        self.eval.set_memo(capacity);
+    }
+
+    fn memo_stats(&self) -> Option<MemoStats> {
+        self.eval.memo_stats()
+    }
+
+    // each step is one generation, so it costs one energy calculation
+    // per individual that isn't elite
+    fn advance(&mut self) -> Option<(St, f64)> {
+        let mut next: Vec<(St, f64)> = self.pop[.. self.elite].to_vec();
+        while next.len() < self.pop.len() {
+            let a = self.pick();
+            let b = self.pick();
+            let mut child = self.pop[a].0.crossover(&self.pop[b].0, &self.glob, &mut self.rng);
+            if self.rng.gen_bool(self.mutation) {
+                child = random_succ(&mut self.rng, &child, &self.glob);
+            }
+            let ind = self.evaluate(child);
+            next.push(ind);
+        }
+        self.pop = next;
+        self.sort();
+        Some(self.pop[0].clone())
+    }
+}
@@ -1,6 +1,26 @@
 pub mod anneal;
+pub mod tempering;
+pub mod parallel;
+pub mod tabu;
+pub mod genetic;
+pub mod stop;
+pub mod memo;
+pub mod observe;
+pub mod top;
+pub mod portable;
+#[cfg(test)]
+mod toy;
 
-pub trait SearchState: Clone + Eq {
+use rand::Rng;
+use std::hash::Hash;
+use memo::MemoStats;
+
+// the rng the front ends use, so that a seed gives the same search everywhere.
+// SmallRng is whatever rand thinks is fastest on the platform and may change between versions;
+// this is a fixed algorithm (PCG 64-bit MCG) that doesn't.
+pub type PortableRng = rand_pcg::Pcg64Mcg;
+
+pub trait SearchState: Clone + Eq + Hash {
     type Glob;
     type Buf: ReusableBuffer;
     type Iter: Iterator<Item=Self>;
@@ -8,6 +28,20 @@ pub trait SearchState: Clone + Eq {
     fn successors(&self, glob: &<Self as SearchState>::Glob) -> <Self as SearchState>::Iter;
 }
 
+// opt-in for solvers that need to know what a move changed, like tabu search.
+// `move_attrs` lists the things that differ between `self` and `succ`
+// (e.g. which items were moved in or out), in whatever terms suit the state.
+pub trait MoveAttrs: SearchState {
+    type Attr: Clone + Eq;
+    fn move_attrs(&self, succ: &Self) -> Vec<Self::Attr>;
+}
+
+// opt-in for population-based solvers like the genetic one.
+// `crossover` makes a child that takes some of its makeup from each parent.
+pub trait Crossover: SearchState {
+    fn crossover<R: Rng + ?Sized>(&self, other: &Self, glob: &Self::Glob, rng: &mut R) -> Self;
+}
+
 pub trait ReusableBuffer {
     fn create() -> Self;
     fn refresh(&mut self);
@@ -17,6 +51,20 @@ pub trait SimpleIterSolver<St: SearchState> {
     type Pm;
     fn org(origin: St, glob: St::Glob, params: Self::Pm) -> Self;
     fn advance(&mut self) -> Option<(St, f64)>;
+    // for solvers that cool as they go
+    fn temperature(&self) -> Option<f64> {
+        None
+    }
+    // for solvers that accept or reject moves, the recent fraction accepted
+    fn accept_rate(&self) -> Option<f64> {
+        None
+    }
+    // for solvers that can remember energies they've already worked out;
+    // see memo::Memo
+    fn set_memo(&mut self, _capacity: usize) {}
+    fn memo_stats(&self) -> Option<MemoStats> {
+        None
+    }
 }
 
 pub fn search_n<St: SearchState, Sv: SimpleIterSolver<St>>(solver: &mut Sv, n: u32)
@@ -40,3 +88,35 @@ pub fn search_n<St: SearchState, Sv: SimpleIterSolver<St>>(solver: &mut Sv, n: u
     best
 }
 
+
+// steepest descent: keep taking the best improving successor until there isn't one.
+// returns the local optimum, its energy, and how many moves it took to get there;
+// zero moves means `state` was already locally optimal.
+pub fn hill_climb<St: SearchState>(state: St, glob: &St::Glob) -> (St, f64, u32) {
+    let mut buf = St::Buf::create();
+    let mut energy = state.energy(glob, &mut buf);
+    let mut state = state;
+    let mut improvements = 0;
+    loop {
+        let mut best: Option<(St, f64)> = None;
+        for t in state.successors(glob) {
+            buf.refresh();
+            let te = t.energy(glob, &mut buf);
+            let bar = match best {
+                Some((_, be)) => be,
+                None => energy,
+            };
+            if te < bar {
+                best = Some((t, te));
+            }
+        }
+        match best {
+            Some((t, te)) => {
+                state = t;
+                energy = te;
+                improvements += 1;
+            },
+            None => return (state, energy, improvements),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{SearchState, ReusableBuffer};
+use std::collections::HashMap;
+use std::hash::Hash;
+use serde::Serialize;
+
+// a bounded cache of the energies of states already seen.
+// when it is full, the clock algorithm picks what to forget:
+// a hand sweeps over the entries, giving a second chance to ones that were used
+// since it last passed, and evicting the first one that wasn't.
+pub struct Memo<St> {
+    capacity: usize,
+    index: HashMap<St, usize>, // state -> position in entries
+    entries: Vec<(St, f64, bool)>, // state, energy, used since the hand last passed
+    hand: usize,
+    hits: u64,
+    misses: u64,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MemoStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub len: usize,
+    pub capacity: usize,
+}
+
+impl MemoStats {
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+
+    // for adding up the stats of several runs
+    pub fn combine(&self, other: &MemoStats) -> MemoStats {
+        MemoStats {
+            hits: self.hits + other.hits,
+            misses: self.misses + other.misses,
+            len: self.len.max(other.len),
+            capacity: self.capacity.max(other.capacity),
+        }
+    }
+}
+
+impl<St: Hash + Eq + Clone> Memo<St> {
+    pub fn new(capacity: usize) -> Memo<St> {
+        let capacity = capacity.max(1);
+        Memo {
+            capacity,
+            index: HashMap::with_capacity(capacity),
+            entries: Vec::with_capacity(capacity),
+            hand: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn get(&mut self, st: &St) -> Option<f64> {
+        match self.index.get(st) {
+            Some(&i) => {
+                self.hits += 1;
+                self.entries[i].2 = true;
+                Some(self.entries[i].1)
+            },
+            None => {
+                self.misses += 1;
+                None
+            },
+        }
+    }
+
+    pub fn insert(&mut self, st: St, energy: f64) {
+        if let Some(&i) = self.index.get(&st) {
+            self.entries[i].1 = energy;
+            return;
+        }
+        if self.entries.len() < self.capacity {
+            self.index.insert(st.clone(), self.entries.len());
+            self.entries.push((st, energy, false));
+            return;
+        }
+        while self.entries[self.hand].2 {
+            self.entries[self.hand].2 = false;
+            self.hand = (self.hand + 1) % self.capacity;
+        }
+        let i = self.hand;
+        self.index.remove(&self.entries[i].0);
+        self.index.insert(st.clone(), i);
+        self.entries[i] = (st, energy, false);
+        self.hand = (self.hand + 1) % self.capacity;
+    }
+
+    pub fn stats(&self) -> MemoStats {
+        MemoStats {
+            hits: self.hits,
+            misses: self.misses,
+            len: self.entries.len(),
+            capacity: self.capacity,
+        }
+    }
+}
+
+// what solvers use to work out energies: the state's reusable buffer,
+// plus a memo if one was asked for
+pub struct Evaluator<St: SearchState> {
+    buf: St::Buf,
+    memo: Option<Memo<St>>,
+}
+
+impl<St: SearchState> Evaluator<St> {
+    pub fn new() -> Evaluator<St> {
+        Evaluator { buf: St::Buf::create(), memo: None }
+    }
+
+    pub fn set_memo(&mut self, capacity: usize) {
+        self.memo = Some(Memo::new(capacity));
+    }
+
+    pub fn energy(&mut self, st: &St, glob: &St::Glob) -> f64 {
+        if let Some(e) = self.memo.as_mut().and_then(|memo| memo.get(st)) {
+            return e;
+        }
+        self.buf.refresh();
+        let e = st.energy(glob, &mut self.buf);
+        if let Some(ref mut memo) = self.memo {
+            memo.insert(st.clone(), e);
+        }
+        e
+    }
+
+    pub fn memo_stats(&self) -> Option<MemoStats> {
+        self.memo.as_ref().map(|memo| memo.stats())
+    }
+}
+
+impl<St: SearchState> Default for Evaluator<St> {
+    fn default() -> Evaluator<St> {
+        Evaluator::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hits_and_misses() {
+        let mut memo = Memo::new(4);
+        assert_eq!(memo.get(&1), None);
+        memo.insert(1, 10.0);
+        memo.insert(2, 20.0);
+        assert_eq!(memo.get(&1), Some(10.0));
+        assert_eq!(memo.get(&3), None);
+        // inserting a state that's already there just updates it
+        memo.insert(1, 11.0);
+        assert_eq!(memo.get(&1), Some(11.0));
+        assert_eq!(memo.stats(), MemoStats { hits: 2, misses: 2, len: 2, capacity: 4 });
+        assert_eq!(memo.stats().hit_rate(), 0.5);
+        assert_eq!(Memo::<u32>::new(0).stats().capacity, 1);
+    }
+
+    #[test]
+    fn second_chances() {
+        let mut memo = Memo::new(3);
+        for st in 1 ..= 3 {
+            memo.insert(st, st as f64);
+        }
+        // 1 and 3 were used since the hand last passed, so it clears them and evicts 2
+        memo.get(&1);
+        memo.get(&3);
+        memo.insert(4, 4.0);
+        assert_eq!((memo.get(&2), memo.get(&1), memo.get(&3), memo.get(&4)), (None, Some(1.0), Some(3.0), Some(4.0)));
+        // 4 took 2's place, and the hand moved on to 3: everything was just used, so it goes all the way
+        // round clearing them and evicts 3, where it started
+        memo.insert(5, 5.0);
+        assert_eq!(memo.get(&3), None);
+        // after which 1 and 4 are unused again (the get(&3) miss doesn't count), so 1 goes next
+        memo.insert(6, 6.0);
+        assert_eq!((memo.get(&1), memo.get(&4), memo.get(&5), memo.get(&6)), (None, Some(4.0), Some(5.0), Some(6.0)));
+        assert_eq!(memo.stats().len, 3);
+    }
+
+    #[test]
+    fn stats_add_up() {
+        let a = MemoStats { hits: 3, misses: 1, len: 2, capacity: 8 };
+        let b = MemoStats { hits: 1, misses: 3, len: 5, capacity: 4 };
+        assert_eq!(a.combine(&b), MemoStats { hits: 4, misses: 4, len: 5, capacity: 8 });
+        assert_eq!(a.hit_rate(), 0.75);
+        assert_eq!(MemoStats::default().hit_rate(), 0.0);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{SearchState, SimpleIterSolver};
+use crate::memo::MemoStats;
+use crate::stop::{Stop, StopReason, Watch};
+use crate::top::TopK;
+use serde::Serialize;
+
+// a snapshot of a search, handed to an Observer after every step
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct Progress {
+    pub step: u64,
+    pub fraction: Option<f64>, // of the step or time budget used up, see Watch::progress
+    pub temperature: Option<f64>,
+    pub current: f64, // energy of the state the solver just returned
+    pub best: f64,
+    pub accept_rate: Option<f64>,
+    pub memo: Option<MemoStats>,
+}
+
+// something that wants to watch a search go, e.g. to draw a progress bar or log a convergence curve.
+// observe is called after every step, so anything slow should only happen every so often.
+pub trait Observer {
+    fn observe(&mut self, _progress: &Progress) {}
+    fn finish(&mut self, _progress: &Progress, _reason: StopReason) {}
+}
+
+impl Observer for () {}
+
+impl<O: Observer + ?Sized> Observer for &mut O {
+    fn observe(&mut self, progress: &Progress) {
+        (**self).observe(progress);
+    }
+
+    fn finish(&mut self, progress: &Progress, reason: StopReason) {
+        (**self).finish(progress, reason);
+    }
+}
+
+impl<O: Observer> Observer for Option<O> {
+    fn observe(&mut self, progress: &Progress) {
+        if let Some(o) = self {
+            o.observe(progress);
+        }
+    }
+
+    fn finish(&mut self, progress: &Progress, reason: StopReason) {
+        if let Some(o) = self {
+            o.finish(progress, reason);
+        }
+    }
+}
+
+impl<A: Observer, B: Observer> Observer for (A, B) {
+    fn observe(&mut self, progress: &Progress) {
+        self.0.observe(progress);
+        self.1.observe(progress);
+    }
+
+    fn finish(&mut self, progress: &Progress, reason: StopReason) {
+        self.0.finish(progress, reason);
+        self.1.finish(progress, reason);
+    }
+}
+
+// search_until, telling `observer` how it's going
+pub fn search_observed<St, Sv, O>(solver: &mut Sv, stop: Stop, observer: &mut O)
+-> (Option<(St, f64)>, StopReason)
+where St: SearchState,
+      Sv: SimpleIterSolver<St>,
+      O: Observer {
+    search_observed_top(solver, stop, observer, &mut TopK::nothing())
+}
+
+// search_observed, also offering every state the solver returns to `top`
+pub fn search_observed_top<St, Sv, O>(solver: &mut Sv, stop: Stop, observer: &mut O, top: &mut TopK<St>)
+-> (Option<(St, f64)>, StopReason)
+where St: SearchState,
+      Sv: SimpleIterSolver<St>,
+      O: Observer {
+    let mut watch = Watch::new(stop);
+    let mut best: Option<(St, f64)> = None;
+    let mut progress = Progress {
+        step: 0,
+        fraction: None,
+        temperature: solver.temperature(),
+        current: 0.0,
+        best: 0.0,
+        accept_rate: None,
+        memo: None,
+    };
+    loop {
+        if let Some(reason) = watch.check() {
+            observer.finish(&progress, reason);
+            return (best, reason);
+        }
+        let new = match solver.advance() {
+            Some(new) => new,
+            None => {
+                observer.finish(&progress, StopReason::Exhausted);
+                return (best, StopReason::Exhausted);
+            },
+        };
+        watch.record(new.1, solver.temperature());
+        top.offer(&new.0, new.1);
+        let improved = match best {
+            Some(ref v) => new.1 < v.1,
+            None => true,
+        };
+        progress = Progress {
+            step: watch.steps(),
+            fraction: watch.progress(),
+            temperature: solver.temperature(),
+            current: new.1,
+            best: if improved { new.1 } else { progress.best },
+            accept_rate: solver.accept_rate(),
+            memo: solver.memo_stats(),
+        };
+        if improved {
+            best = Some(new);
+        }
+        observer.observe(&progress);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{SearchState, SimpleIterSolver};
+use crate::stop::{Stop, Watch};
+use crate::memo::MemoStats;
+use crate::top::TopK;
+use std::sync::{Mutex, MutexGuard};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::panic;
+use std::time::Duration;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ThreadStats {
+    pub runs: usize,
+    pub steps: u64,
+    pub best: Option<f64>, // best energy this thread found
+    pub memo: Option<MemoStats>, // added up over all of this thread's runs
+}
+
+pub struct ParallelResult<St> {
+    pub best: Option<(St, f64)>,
+    pub top: TopK<St>, // merged from every run
+    pub threads: Vec<ThreadStats>,
+}
+
+// runs `runs` independent searches, each stopping according to `stop`, spread over `threads` threads.
+// `make_solver` is given the index of the run (0 .. runs) and should return a fresh solver;
+// give each run its own rng seed or they will all do the same thing.
+// every run offers the states it visits to its own copy of `top` (see observe::search_observed_top),
+// and they're all merged at the end; pass TopK::nothing() if only the best state matters.
+// while the threads work, the calling thread calls `progress` now and then
+// with the total steps taken so far and the best energy seen by any thread.
+// if a run panics, the panic is passed on to the caller once every thread has stopped.
+pub fn search_parallel<St, Sv, F, P>(
+    threads: usize, runs: usize, stop: Stop, top: TopK<St>, make_solver: F, mut progress: P
+) -> ParallelResult<St>
+where St: SearchState + Send,
+      Sv: SimpleIterSolver<St>,
+      F: Fn(usize) -> Sv + Sync,
+      P: FnMut(u64, Option<f64>) {
+    let threads = threads.max(1).min(runs.max(1));
+    let best: Mutex<Option<(St, f64)>> = Mutex::new(None);
+    let merged = Mutex::new(top);
+    let next_run = AtomicUsize::new(0);
+    let steps_done = AtomicU64::new(0);
+
+    let stats = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0 .. threads).map(|_| scope.spawn(|| {
+            let mut stats = ThreadStats { runs: 0, steps: 0, best: None, memo: None };
+            loop {
+                let run = next_run.fetch_add(1, Ordering::Relaxed);
+                if run >= runs {
+                    break;
+                }
+                let mut solver = make_solver(run);
+                let mut run_best: Option<(St, f64)> = None;
+                let mut run_top = lock(&merged).empty_like();
+                let mut watch = Watch::new(stop);
+                while watch.check().is_none() {
+                    let new = match solver.advance() {
+                        Some(new) => new,
+                        None => break,
+                    };
+                    watch.record(new.1, solver.temperature());
+                    steps_done.fetch_add(1, Ordering::Relaxed);
+                    run_top.offer(&new.0, new.1);
+                    let improved = match run_best {
+                        Some(ref v) => new.1 < v.1,
+                        None => true,
+                    };
+                    if improved {
+                        share(&best, &new);
+                        run_best = Some(new);
+                    }
+                }
+                lock(&merged).merge(run_top);
+                stats.runs += 1;
+                stats.steps += watch.steps();
+                if let Some(m) = solver.memo_stats() {
+                    stats.memo = Some(stats.memo.map_or(m, |sm| sm.combine(&m)));
+                }
+                if let Some((_, e)) = run_best {
+                    stats.best = Some(stats.best.map_or(e, |b: f64| b.min(e)));
+                }
+            }
+            stats
+        })).collect();
+
+        // polling the handles rather than counting finished threads, so that a thread that panics counts too
+        while !handles.iter().all(|h| h.is_finished()) {
+            let best_e = lock(&best).as_ref().map(|v| v.1);
+            progress(steps_done.load(Ordering::Relaxed), best_e);
+            std::thread::sleep(Duration::from_millis(50));
+        }
+
+        let joined: Vec<_> = handles.into_iter().map(|h| h.join()).collect();
+        joined.into_iter()
+            .map(|stats| stats.unwrap_or_else(|payload| panic::resume_unwind(payload)))
+            .collect()
+    });
+
+    ParallelResult {
+        best: best.into_inner().unwrap_or_else(|e| e.into_inner()),
+        top: merged.into_inner().unwrap_or_else(|e| e.into_inner()),
+        threads: stats,
+    }
+}
+
+// a thread that panics while holding a lock poisons it. what it was doing is lost with it,
+// but the other threads carry on, so that the panic passed on is that one and not a PoisonError
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn share<St: Clone>(best: &Mutex<Option<(St, f64)>>, new: &(St, f64)) {
+    let mut best = lock(best);
+    let improved = match *best {
+        Some(ref v) => new.1 < v.1,
+        None => true,
+    };
+    if improved {
+        *best = Some(new.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toy::{Walk, Stepper};
+    use std::sync::atomic::AtomicBool;
+
+    #[test]
+    fn finds_the_best_run() {
+        let result = search_parallel(
+            3, 6, Stop::steps(4), TopK::nothing(),
+            |run| Stepper::org(Walk(10 + run as i64), (), None), |_, _| {}
+        );
+        assert_eq!(result.best, Some((Walk(6), 6.0)));
+        assert_eq!(result.threads.iter().map(|t| t.runs).sum::<usize>(), 6);
+        assert_eq!(result.threads.iter().map(|t| t.steps).sum::<u64>(), 24);
+    }
+
+    #[test]
+    fn passes_on_panics() {
+        let caught = panic::catch_unwind(|| {
+            search_parallel(
+                2, 4, Stop::steps(100), TopK::nothing(),
+                |run| Stepper::org(Walk(1000), (), if run == 2 { Some(5) } else { None }), |_, _| {}
+            )
+        });
+        let payload = caught.err().expect("the panic should reach the caller");
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"stepper told to panic"));
+    }
+
+    static MERGE_PANICKED: AtomicBool = AtomicBool::new(false);
+
+    // runs start a thousand apart, so only merging two runs' states compares states this far apart,
+    // and the first time that happens it panics, with the lock on the merged TopK held
+    fn panics_merging(a: &Walk, b: &Walk) -> usize {
+        let distance = (a.0 - b.0).unsigned_abs() as usize;
+        if distance > 500 && !MERGE_PANICKED.swap(true, Ordering::Relaxed) {
+            panic!("distance told to panic");
+        }
+        distance
+    }
+
+    // the other thread still has to take that lock afterwards, to start runs and merge its own,
+    // and it should get through every run that's left rather than panic on the poisoned lock
+    #[test]
+    fn passes_on_panics_while_merging() {
+        let started = AtomicUsize::new(0);
+        let caught = panic::catch_unwind(|| {
+            search_parallel(
+                2, 6, Stop::steps(20), TopK::new(100, 1, panics_merging),
+                |run| {
+                    started.fetch_add(1, Ordering::Relaxed);
+                    Stepper::org(Walk(1000 * (run as i64 + 1)), (), None)
+                },
+                |_, _| {}
+            )
+        });
+        let payload = caught.err().expect("the panic should reach the caller");
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"distance told to panic"));
+        assert_eq!(started.load(Ordering::Relaxed), 6);
+    }
+}
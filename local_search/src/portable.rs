@@ -0,0 +1,90 @@
+// rand 0.7 samples a usize as a u32 on 32 bit platforms and as a u64 on 64 bit ones,
+// so the same seed would pick differently in the browser (wasm32) than on the command line.
+// these always sample at the width a 64 bit platform would, whatever the platform.
+use rand::Rng;
+use rand::distributions::{Distribution, Uniform};
+
+// a number in 0 .. n, like rng.gen_range(0, n)
+pub fn below<R: Rng + ?Sized>(rng: &mut R, n: usize) -> usize {
+    rng.gen_range(0, n as u64) as usize
+}
+
+// like Uniform::new(0, n), for usize
+#[derive(Debug, Copy, Clone)]
+pub struct UniformIndex(Uniform<u64>);
+
+impl UniformIndex {
+    pub fn new(n: usize) -> UniformIndex {
+        UniformIndex(Uniform::new(0, n as u64))
+    }
+}
+
+impl Distribution<usize> for UniformIndex {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        self.0.sample(rng) as usize
+    }
+}
+
+// SliceRandom::choose and shuffle already draw a u32 for anything shorter than 2^32 on every platform;
+// these do the same without counting on it.
+
+pub fn choose<'a, T, R: Rng + ?Sized>(rng: &mut R, items: &'a [T]) -> Option<&'a T> {
+    if items.is_empty() {
+        None
+    } else {
+        Some(&items[rng.gen_range(0, items.len() as u32) as usize])
+    }
+}
+
+pub fn shuffle<T, R: Rng + ?Sized>(rng: &mut R, items: &mut [T]) {
+    for i in (1 .. items.len()).rev() {
+        items.swap(i, rng.gen_range(0, i as u32 + 1) as usize);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{RngCore, SeedableRng};
+    use rand::seq::SliceRandom;
+    use crate::PortableRng;
+
+    // the same on every platform; if these change, so does every seed anyone has written down
+    #[test]
+    fn fixed_draws() {
+        let mut rng = PortableRng::seed_from_u64(41);
+        let raw: Vec<u64> = (0 .. 3).map(|_| rng.next_u64()).collect();
+        assert_eq!(raw, [1101944173988824219, 14608844570433372281, 8797601464019862584]);
+        let below: Vec<usize> = (0 .. 8).map(|_| below(&mut rng, 1000)).collect();
+        assert_eq!(below, [970, 748, 240, 159, 800, 561, 779, 863]);
+        let dist = UniformIndex::new(9);
+        let uniform: Vec<usize> = (0 .. 8).map(|_| rng.sample(dist)).collect();
+        assert_eq!(uniform, [8, 0, 7, 0, 1, 5, 7, 4]);
+        let mut items: Vec<usize> = (0 .. 9).collect();
+        shuffle(&mut rng, &mut items);
+        assert_eq!(items, [0, 7, 6, 2, 4, 3, 8, 1, 5]);
+        assert_eq!(choose(&mut rng, &items), Some(&6));
+    }
+
+    // and the same as what rand itself does on 64 bit platforms, which is what seeds were made with
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn same_as_rand() {
+        let mut ours = PortableRng::seed_from_u64(5);
+        let mut theirs = PortableRng::seed_from_u64(5);
+        for n in 1 .. 200 {
+            assert_eq!(below(&mut ours, n), theirs.gen_range(0, n));
+        }
+        let dist = UniformIndex::new(77);
+        let their_dist = Uniform::new(0usize, 77);
+        for _ in 0 .. 100 {
+            assert_eq!(ours.sample(dist), theirs.sample(their_dist));
+        }
+        let mut a: Vec<u32> = (0 .. 50).collect();
+        let mut b = a.clone();
+        shuffle(&mut ours, &mut a);
+        b.shuffle(&mut theirs);
+        assert_eq!(a, b);
+        assert_eq!(choose(&mut ours, &a), b.choose(&mut theirs));
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{SearchState, SimpleIterSolver};
+use std::time::{Duration, Instant};
+
+// when a search should give up. every criterion that is set applies,
+// and the search stops as soon as any one of them is met,
+// so leaving all of them unset means searching forever.
+// the first step is always taken, whatever the criteria, so that there's a best state to return.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Stop {
+    pub steps: Option<u64>,
+    pub time: Option<Duration>, // don't set this on wasm, which has no clock
+    pub stall: Option<u64>, // steps in a row without beating the best energy so far
+    pub target: Option<f64>, // energy that is good enough
+    pub min_temp: Option<f64>, // only means anything to solvers that have a temperature
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StopReason {
+    Steps,
+    Time,
+    Stall,
+    Target,
+    MinTemp,
+    Exhausted, // the solver had nothing left to try
+}
+
+impl Stop {
+    pub fn steps(n: u64) -> Stop {
+        Stop { steps: Some(n), .. Stop::default() }
+    }
+}
+
+// keeps track of a search in progress on behalf of a Stop
+#[derive(Debug, Clone)]
+pub struct Watch {
+    stop: Stop,
+    started: Option<Instant>, // only looked at if there is a time limit
+    steps: u64,
+    stalled: u64,
+    best: Option<f64>,
+    temp: Option<f64>,
+}
+
+impl Watch {
+    pub fn new(stop: Stop) -> Watch {
+        Watch {
+            stop,
+            started: stop.time.map(|_| Instant::now()),
+            steps: 0,
+            stalled: 0,
+            best: None,
+            temp: None,
+        }
+    }
+
+    // call after every step with the energy the solver returned and its temperature
+    pub fn record(&mut self, energy: f64, temp: Option<f64>) {
+        self.steps += 1;
+        self.temp = temp;
+        match self.best {
+            Some(b) if energy >= b => self.stalled += 1,
+            _ => {
+                self.best = Some(energy);
+                self.stalled = 0;
+            },
+        }
+    }
+
+    // call before every step; says why not to take it, if there's a reason
+    pub fn check(&self) -> Option<StopReason> {
+        if self.steps == 0 {
+            return None;
+        }
+        let stop = &self.stop;
+        if let Some(n) = stop.steps {
+            if self.steps >= n {
+                return Some(StopReason::Steps);
+            }
+        }
+        if let (Some(best), Some(target)) = (self.best, stop.target) {
+            if best <= target {
+                return Some(StopReason::Target);
+            }
+        }
+        if let Some(n) = stop.stall {
+            if self.stalled >= n {
+                return Some(StopReason::Stall);
+            }
+        }
+        if let (Some(temp), Some(min)) = (self.temp, stop.min_temp) {
+            if temp <= min {
+                return Some(StopReason::MinTemp);
+            }
+        }
+        if let (Some(started), Some(limit)) = (self.started, stop.time) {
+            if started.elapsed() >= limit {
+                return Some(StopReason::Time);
+            }
+        }
+        None
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    // how much of the step or time budget is used up, from 0 to 1,
+    // whichever is further along. None if neither is set.
+    pub fn progress(&self) -> Option<f64> {
+        let by_steps = self.stop.steps.map(|n| self.steps as f64 / n.max(1) as f64);
+        let by_time = match (self.started, self.stop.time) {
+            (Some(started), Some(limit)) => Some(started.elapsed().as_secs_f64() / limit.as_secs_f64().max(1e-9)),
+            _ => None,
+        };
+        match (by_steps, by_time) {
+            (Some(a), Some(b)) => Some(a.max(b).min(1.0)),
+            (a, b) => a.or(b).map(|p| p.min(1.0)),
+        }
+    }
+}
+
+// like search_n, but stops according to `stop`, and says why it stopped
+pub fn search_until<St: SearchState, Sv: SimpleIterSolver<St>>(solver: &mut Sv, stop: Stop)
+-> (Option<(St, f64)>, StopReason) {
+    crate::observe::search_observed(solver, stop, &mut ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_step_always_taken() {
+        let stop = Stop { steps: Some(0), time: Some(Duration::from_secs(0)), target: Some(1.0), .. Stop::default() };
+        let mut watch = Watch::new(stop);
+        assert_eq!(watch.check(), None);
+        watch.record(5.0, None);
+        assert_eq!(watch.check(), Some(StopReason::Steps));
+    }
+
+    #[test]
+    fn stops_on_stall_and_target() {
+        let mut watch = Watch::new(Stop { stall: Some(2), target: Some(-10.0), .. Stop::default() });
+        watch.record(0.0, None);
+        watch.record(1.0, None);
+        assert_eq!(watch.check(), None);
+        watch.record(0.0, None);
+        assert_eq!(watch.check(), Some(StopReason::Stall));
+        watch.record(-10.0, None);
+        assert_eq!(watch.check(), Some(StopReason::Target));
+    }
+}
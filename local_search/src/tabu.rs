@@ -0,0 +1,126 @@
+use crate::{MoveAttrs, SimpleIterSolver};
+use crate::memo::{Evaluator, MemoStats};
+use crate::anneal::random_succ;
+use rand::Rng;
+
+// tabu search: every step looks at the neighbourhood and takes the best move
+// that doesn't touch anything a recent move touched, even if that move is uphill.
+// a tabu move is still allowed if it would beat the best energy seen so far.
+//
+// with `sample: None` the whole neighbourhood is scanned each step,
+// which means the best state visited is guaranteed to be a local optimum
+// once the search has moved away from it at least once.
+pub struct Params<R: Rng> {
+    pub rng: R,
+    pub tenure: u64, // number of steps a move's attributes stay tabu
+    pub sample: Option<usize>, // look at this many random neighbours instead of all of them
+}
+
+pub struct Tabu<St: MoveAttrs, R: Rng> {
+    glob: St::Glob,
+    eval: Evaluator<St>,
+    rng: R,
+    tenure: u64,
+    sample: Option<usize>,
+    step: u64,
+    tabu: Vec<(St::Attr, u64)>, // attribute, step at which it stops being tabu
+    best: f64,
+    last: (St, f64),
+}
+
+impl<St: MoveAttrs, R: Rng> Tabu<St, R> {
+    fn is_tabu(&self, attrs: &[St::Attr]) -> bool {
+        attrs.iter().any(|a| self.tabu.iter().any(|(t, _)| t == a))
+    }
+
+    fn candidates(&mut self) -> Vec<St> {
+        if let Some(n) = self.sample {
+            (0 .. n).map(|_| random_succ(&mut self.rng, &self.last.0, &self.glob)).collect()
+        } else {
+            self.last.0.successors(&self.glob).collect()
+        }
+    }
+}
+
+impl<St: MoveAttrs, R: Rng> SimpleIterSolver<St> for Tabu<St, R> {
+    type Pm = Params<R>;
+    fn org(origin: St, glob: St::Glob, params: Params<R>) -> Tabu<St, R> {
+        let mut eval = Evaluator::new();
+        let org_energy = eval.energy(&origin, &glob);
+        Tabu {
+            glob,
+            eval,
+            rng: params.rng,
+            tenure: params.tenure,
+            sample: params.sample,
+            step: 0,
+            tabu: Vec::new(),
+            best: org_energy,
+            last: (origin, org_energy),
+        }
+    }
+
+    fn set_memo(&mut self, capacity: usize) {
+        self.eval.set_memo(capacity);
+    }
+
+    fn memo_stats(&self) -> Option<MemoStats> {
+        self.eval.memo_stats()
+    }
+
+    fn advance(&mut self) -> Option<(St, f64)> {
+        let step = self.step;
+        self.tabu.retain(|&(_, until)| until > step);
+
+        let mut chosen: Option<(St, f64, Vec<St::Attr>)> = None;
+        for t in self.candidates() {
+            let te = self.eval.energy(&t, &self.glob);
+            if let Some((_, ce, _)) = chosen {
+                if te >= ce {
+                    continue;
+                }
+            }
+            let attrs = self.last.0.move_attrs(&t);
+            if te < self.best || !self.is_tabu(&attrs) {
+                chosen = Some((t, te, attrs));
+            }
+        }
+
+        // if every move was tabu, stay put and let the list drain
+        if let Some((t, te, attrs)) = chosen {
+            let until = self.step + self.tenure;
+            self.tabu.extend(attrs.into_iter().map(|a| (a, until)));
+            self.best = self.best.min(te);
+            self.last = (t, te);
+        }
+        self.step += 1;
+
+        Some((self.last.0.clone(), self.last.1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toy::Walk;
+    use crate::PortableRng;
+    use rand::SeedableRng;
+
+    fn walk(tenure: u64, steps: usize) -> Vec<i64> {
+        let params = Params { rng: PortableRng::seed_from_u64(33), tenure, sample: None };
+        let mut tabu = Tabu::org(Walk(3), (), params);
+        (0 .. steps).map(|_| {
+            let (Walk(x), _) = tabu.advance().unwrap();
+            x
+        }).collect()
+    }
+
+    #[test]
+    fn tenure_and_aspiration() {
+        // with tenure 3: going left stays tabu, but each step left beats the best so far, so it's taken anyway.
+        // at 0 it has to go right, then both ways are tabu and it waits at 1 until going left isn't
+        assert_eq!(walk(3, 6), [2, 1, 0, 1, 1, 0]);
+        // nothing is ever tabu, so from 0 it takes the first of two equally bad moves, and comes straight back
+        assert_eq!(walk(0, 6), [2, 1, 0, -1, 0, -1]);
+    }
+}
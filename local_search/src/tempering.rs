@@ -0,0 +1,166 @@
+use crate::{SearchState, SimpleIterSolver};
+use crate::memo::{Evaluator, MemoStats};
+use crate::anneal::{random_succ, p};
+use rand::Rng;
+
+// parallel tempering, a.k.a. replica exchange:
+// one Metropolis chain per temperature, which never cool,
+// with neighbouring chains periodically offered the chance to trade states.
+// good states found by hot chains can then sink down to the cold ones,
+// and cold chains stuck in a basin can be pulled back out.
+pub struct Params<R: Rng> {
+    pub rng: R,
+    pub temps: Vec<f64>, // one chain per temperature
+    pub swap_interval: u32, // steps between swap attempts
+}
+
+// `k` temperatures spaced geometrically from `t_min` to `t_max`, coldest first
+pub fn ladder(t_min: f64, t_max: f64, k: usize) -> Vec<f64> {
+    if k < 2 {
+        return vec![t_min];
+    }
+    let ratio = (t_max / t_min).powf(1.0 / (k - 1) as f64);
+    (0 .. k).map(|i| t_min * ratio.powi(i as i32)).collect()
+}
+
+pub struct Tempering<St: SearchState, R: Rng> {
+    glob: St::Glob,
+    eval: Evaluator<St>,
+    rng: R,
+    temps: Vec<f64>,
+    chains: Vec<(St, f64)>, // same order as temps
+    swap_interval: u32,
+    countdown: u32,
+    swaps_tried: u64,
+    swaps_made: u64,
+}
+
+impl<St: SearchState, R: Rng> Tempering<St, R> {
+    // fraction of swap attempts that were accepted;
+    // if this is very low the temperatures are too far apart
+    pub fn swap_rate(&self) -> f64 {
+        if self.swaps_tried == 0 {
+            0.0
+        } else {
+            self.swaps_made as f64 / self.swaps_tried as f64
+        }
+    }
+
+    fn try_swaps(&mut self) {
+        for i in 0 .. self.chains.len().saturating_sub(1) {
+            let (ei, ej) = (self.chains[i].1, self.chains[i + 1].1);
+            let (bi, bj) = (1.0 / self.temps[i], 1.0 / self.temps[i + 1]);
+            let pt = ((bi - bj) * (ei - ej)).exp().min(1.0);
+            self.swaps_tried += 1;
+            if self.rng.gen_bool(pt) {
+                self.chains.swap(i, i + 1);
+                self.swaps_made += 1;
+            }
+        }
+    }
+}
+
+impl<St: SearchState, R: Rng> SimpleIterSolver<St> for Tempering<St, R> {
+    type Pm = Params<R>;
+    fn org(origin: St, glob: St::Glob, mut params: Params<R>) -> Tempering<St, R> {
+        assert!(!params.temps.is_empty());
+        params.temps.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let mut eval = Evaluator::new();
+        let org_energy = eval.energy(&origin, &glob);
+        let chains = vec![(origin, org_energy); params.temps.len()];
+        Tempering {
+            glob,
+            eval,
+            rng: params.rng,
+            temps: params.temps,
+            chains,
+            swap_interval: params.swap_interval.max(1),
+            countdown: params.swap_interval.max(1),
+            swaps_tried: 0,
+            swaps_made: 0,
+        }
+    }
+
+    fn set_memo(&mut self, capacity: usize) {
+        self.eval.set_memo(capacity);
+    }
+
+    fn memo_stats(&self) -> Option<MemoStats> {
+        self.eval.memo_stats()
+    }
+
+    // each step advances every chain once, so it costs one energy calculation per chain.
+    // the state returned is the best among the chains' current states.
+    fn advance(&mut self) -> Option<(St, f64)> {
+        for i in 0 .. self.chains.len() {
+            let t = random_succ(&mut self.rng, &self.chains[i].0, &self.glob);
+            let te = self.eval.energy(&t, &self.glob);
+            let pt = p(self.chains[i].1, te, self.temps[i]);
+            if self.rng.gen_bool(pt) {
+                self.chains[i] = (t, te);
+            }
+        }
+
+        self.countdown -= 1;
+        if self.countdown == 0 {
+            self.try_swaps();
+            self.countdown = self.swap_interval;
+        }
+
+        self.chains.iter()
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toy::Walk;
+    use crate::PortableRng;
+    use rand::SeedableRng;
+
+    fn tempering(seed: u64, swap_interval: u32) -> Tempering<Walk, PortableRng> {
+        let params = Params { rng: PortableRng::seed_from_u64(seed), temps: vec![100.0, 1.0], swap_interval };
+        Tempering::org(Walk(40), (), params)
+    }
+
+    #[test]
+    fn ladders() {
+        assert_eq!(ladder(1.0, 100.0, 3), [1.0, 10.0, 100.0]);
+        assert_eq!(ladder(5.0, 100.0, 1), [5.0]);
+        // and org puts them coldest first whatever order they came in
+        assert_eq!(tempering(1, 1).temps, [1.0, 100.0]);
+    }
+
+    // a colder chain in a worse state than the hotter one next to it always trades with it
+    #[test]
+    fn exchanges_replicas() {
+        let mut t = tempering(1, 1);
+        t.chains = vec![(Walk(30), 30.0), (Walk(3), 3.0)];
+        t.try_swaps();
+        assert_eq!(t.chains, [(Walk(3), 3.0), (Walk(30), 30.0)]);
+        assert_eq!(t.swap_rate(), 1.0);
+    }
+
+    #[test]
+    fn swaps_every_interval() {
+        let mut t = tempering(2, 5);
+        for _ in 0 .. 200 {
+            t.advance();
+        }
+        assert_eq!(t.swaps_tried, 40);
+        assert!(t.swaps_made > 0 && t.swaps_made < 40, "{} swaps", t.swaps_made);
+    }
+
+    #[test]
+    fn same_seed_same_run() {
+        let run = |seed| {
+            let mut t = tempering(seed, 3);
+            let states: Vec<(Walk, f64)> = (0 .. 300).map(|_| t.advance().unwrap()).collect();
+            (states, t.chains, t.swaps_made)
+        };
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7), run(8));
+    }
+}
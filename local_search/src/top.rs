@@ -0,0 +1,165 @@
+use crate::SearchState;
+
+// the best `k` states a search has come across, best (lowest energy) first,
+// no two of them less than `min_distance` apart by `distance`.
+// a state that is too close to one already kept only gets in by beating it, and then replaces it.
+#[derive(Debug, Clone)]
+pub struct TopK<St> {
+    k: usize,
+    min_distance: usize,
+    distance: fn(&St, &St) -> usize,
+    entries: Vec<(St, f64)>,
+}
+
+impl<St: SearchState> TopK<St> {
+    pub fn new(k: usize, min_distance: usize, distance: fn(&St, &St) -> usize) -> TopK<St> {
+        TopK { k, min_distance, distance, entries: Vec::with_capacity(k) }
+    }
+
+    // keeps nothing, for searches that only want the single best state
+    pub fn nothing() -> TopK<St> {
+        TopK::new(0, 0, |_, _| 0)
+    }
+
+    // true if `state` was kept
+    pub fn offer(&mut self, state: &St, energy: f64) -> bool {
+        if self.k == 0 {
+            return false;
+        }
+        if self.entries.len() == self.k && energy >= self.entries[self.k - 1].1 {
+            return false;
+        }
+        let (distance, min_distance) = (self.distance, self.min_distance);
+        let close = |kept: &St| distance(kept, state) < min_distance;
+        if self.entries.iter().any(|(kept, e)| *e <= energy && close(kept)) {
+            return false;
+        }
+        self.entries.retain(|(kept, _)| !close(kept));
+        let at = self.entries.iter().position(|&(_, e)| energy < e).unwrap_or(self.entries.len());
+        self.entries.insert(at, (state.clone(), energy));
+        self.entries.truncate(self.k);
+        true
+    }
+
+    pub fn merge(&mut self, other: TopK<St>) {
+        for (state, energy) in other.entries {
+            self.offer(&state, energy);
+        }
+    }
+
+    pub fn entries(&self) -> &[(St, f64)] {
+        &self.entries
+    }
+
+    pub fn into_entries(self) -> Vec<(St, f64)> {
+        self.entries
+    }
+
+    // an empty TopK that keeps the same kind of states as this one
+    pub fn empty_like(&self) -> TopK<St> {
+        TopK::new(self.k, self.min_distance, self.distance)
+    }
+
+    // the same states, each hill climbed (see crate::hill_climb), kept by the same rules.
+    // climbing can bring two of them too close together, so there may be fewer of them afterwards.
+    pub fn climbed(&self, glob: &St::Glob) -> TopK<St> {
+        let mut climbed = self.empty_like();
+        for (state, _) in self.entries.iter() {
+            let (state, energy, _) = crate::hill_climb(state.clone(), glob);
+            climbed.offer(&state, energy);
+        }
+        climbed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toy::Walk;
+
+    fn apart(a: &Walk, b: &Walk) -> usize {
+        (a.0 - b.0).unsigned_abs() as usize
+    }
+
+    fn kept(top: &TopK<Walk>) -> Vec<i64> {
+        top.entries().iter().map(|(state, _)| state.0).collect()
+    }
+
+    fn offer(top: &mut TopK<Walk>, x: i64) -> bool {
+        top.offer(&Walk(x), x.abs() as f64)
+    }
+
+    #[test]
+    fn keeps_the_best_k_in_order() {
+        let mut top = TopK::new(3, 1, apart);
+        for x in [50, 40, 60, 30, 45] {
+            assert!(offer(&mut top, x));
+        }
+        assert_eq!(kept(&top), [30, 40, 45]);
+        // no better than the last one, when full
+        assert!(!offer(&mut top, 45));
+        assert!(!offer(&mut top, 70));
+        assert_eq!(kept(&top), [30, 40, 45]);
+    }
+
+    #[test]
+    fn close_states_replace_or_are_turned_away() {
+        let mut top = TopK::new(4, 3, apart);
+        for x in [10, 20, 30] {
+            assert!(offer(&mut top, x));
+        }
+        // too close to 20, and no better, whatever room is left
+        assert!(!offer(&mut top, 22));
+        assert!(!offer(&mut top, 20));
+        // too close to 20 but better, so it takes 20's place
+        assert!(offer(&mut top, 18));
+        assert_eq!(kept(&top), [10, 18, 30]);
+        // a state close to two kept ones that beats both replaces both
+        let mut top = TopK::new(4, 3, apart);
+        for x in [-2, 2, 30] {
+            assert!(offer(&mut top, x));
+        }
+        assert!(offer(&mut top, 0));
+        assert_eq!(kept(&top), [0, 30]);
+        // but not if it only beats one of them
+        let mut top = TopK::new(4, 3, apart);
+        for x in [10, 14] {
+            assert!(offer(&mut top, x));
+        }
+        assert!(!offer(&mut top, 12));
+        assert_eq!(kept(&top), [10, 14]);
+    }
+
+    #[test]
+    fn evicts_the_worst_when_full() {
+        let mut top = TopK::new(2, 1, apart);
+        for x in [10, 20, 5] {
+            assert!(offer(&mut top, x));
+        }
+        assert_eq!(kept(&top), [5, 10]);
+    }
+
+    #[test]
+    fn nothing_keeps_nothing() {
+        let mut top = TopK::nothing();
+        assert!(!offer(&mut top, 1));
+        assert!(top.entries().is_empty());
+    }
+
+    #[test]
+    fn merge_and_climb() {
+        let mut a = TopK::new(3, 2, apart);
+        let mut b = a.empty_like();
+        for x in [10, 30] {
+            offer(&mut a, x);
+        }
+        for x in [11, 20, 40] {
+            offer(&mut b, x);
+        }
+        a.merge(b);
+        assert_eq!(kept(&a), [10, 20, 30]);
+        // everything climbs down to 0, so only one is left
+        let climbed = a.climbed(&());
+        assert_eq!(climbed.entries(), [(Walk(0), 0.0)]);
+    }
+}
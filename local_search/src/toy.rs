@@ -0,0 +1,63 @@
+// a tiny search problem for tests: walk along the integers towards zero
+use crate::{SearchState, ReusableBuffer, SimpleIterSolver, MoveAttrs};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Walk(pub i64);
+
+pub struct NoBuf;
+
+impl ReusableBuffer for NoBuf {
+    fn create() -> NoBuf {
+        NoBuf
+    }
+
+    fn refresh(&mut self) {}
+}
+
+impl SearchState for Walk {
+    type Glob = ();
+    type Buf = NoBuf;
+    type Iter = std::vec::IntoIter<Walk>;
+
+    fn energy(&self, _glob: &(), _buf: &mut NoBuf) -> f64 {
+        self.0.abs() as f64
+    }
+
+    fn successors(&self, _glob: &()) -> Self::Iter {
+        vec![Walk(self.0 - 1), Walk(self.0 + 1)].into_iter()
+    }
+}
+
+// a move's attribute is its direction, so tabu search can forbid going back the way it came
+impl MoveAttrs for Walk {
+    type Attr = i64;
+
+    fn move_attrs(&self, succ: &Walk) -> Vec<i64> {
+        vec![succ.0 - self.0]
+    }
+}
+
+// steps towards zero one at a time, then stays there.
+// panics instead of taking step `panic_at`, if that's set.
+pub struct Stepper {
+    at: Walk,
+    steps: u64,
+    panic_at: Option<u64>,
+}
+
+impl SimpleIterSolver<Walk> for Stepper {
+    type Pm = Option<u64>;
+
+    fn org(origin: Walk, _glob: (), panic_at: Option<u64>) -> Stepper {
+        Stepper { at: origin, steps: 0, panic_at }
+    }
+
+    fn advance(&mut self) -> Option<(Walk, f64)> {
+        if self.panic_at == Some(self.steps) {
+            panic!("stepper told to panic");
+        }
+        self.steps += 1;
+        self.at = Walk(self.at.0 - self.at.0.signum());
+        Some((self.at, self.at.energy(&(), &mut NoBuf)))
+    }
+}
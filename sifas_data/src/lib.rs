@@ -3,7 +3,7 @@ pub mod skill_enums;
 pub mod wave_enums;
 pub mod structs;
 
-use misc_enums::Idol;
+use misc_enums::{Idol, Attribute};
 use misc_enums::Idol::*;
 
 // convenience function for sifas percentile values,
@@ -18,6 +18,12 @@ pub const ALL_IDOLS: &[Idol] = &[
     Ayumu, Kasumi, Shizuku, Karin, Ai, Kanata, Setsuna, Emma, Rina, Shioriko
 ];
 
+// every attribute other than neutral
+pub const MAIN_ATTRIBUTES: &[Attribute] = &[
+    Attribute::Smile, Attribute::Pure, Attribute::Cool,
+    Attribute::Active, Attribute::Natural, Attribute::Elegant,
+];
+
 pub mod prelude {
     pub use super::misc_enums::*;
     pub use super::skill_enums::*;
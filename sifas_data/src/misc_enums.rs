@@ -55,6 +55,24 @@ pub enum Attribute {
     Neutral = 9, // why
 }
 
+impl Attribute {
+    // the "XSPCANE" numbering used by the command line,
+    // where 0 is neutral and 1 ..= 6 are smile ... elegant
+    pub fn from_digit(n: u32) -> Option<Attribute> {
+        use Attribute::*;
+        match n {
+            0 => Some(Neutral),
+            1 => Some(Smile),
+            2 => Some(Pure),
+            3 => Some(Cool),
+            4 => Some(Active),
+            5 => Some(Natural),
+            6 => Some(Elegant),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize_repr, Deserialize_repr)]
 #[repr(u8)]
 pub enum Rarity {
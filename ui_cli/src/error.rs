@@ -8,6 +8,8 @@ pub enum Error {
     ApiError(card_consumer::Error),
     BadCmdLine(getopts::Fail),
     IoError(std::io::Error),
+    BadTeam(idolsched::team::TeamError),
+    BadConstraints(idolsched::constraint::ConstraintError),
     StrErr(&'static str),
     Etc(Box<dyn std::error::Error>),
 }
@@ -19,6 +21,8 @@ impl Display for Error {
             ApiError(e) => write!(f, "cards API: {}", e),
             BadCmdLine(e) => write!(f, "incorrect command line argument: {}", e),
             IoError(e) => write!(f, "i/o: {}", e),
+            BadTeam(e) => write!(f, "invalid team: {}", e),
+            BadConstraints(e) => write!(f, "invalid constraints: {}", e),
             StrErr(s) => write!(f, "{}", s),
             Etc(e) => write!(f, "{}", e),
         }
@@ -32,6 +36,8 @@ impl std::error::Error for Error {
             ApiError(e) => Some(e),
             BadCmdLine(e) => Some(e),
             IoError(e) => Some(e),
+            BadTeam(e) => Some(e),
+            BadConstraints(e) => Some(e),
             StrErr(_) => None,
             Etc(_) => None,
         }
@@ -62,3 +68,14 @@ impl From<std::io::Error> for Error {
     }
 }
 
+impl From<idolsched::team::TeamError> for Error {
+    fn from(e: idolsched::team::TeamError) -> Error {
+        BadTeam(e)
+    }
+}
+
+impl From<idolsched::constraint::ConstraintError> for Error {
+    fn from(e: idolsched::constraint::ConstraintError) -> Error {
+        BadConstraints(e)
+    }
+}
@@ -1,18 +1,44 @@
 mod error;
+mod progress;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
 
-use rand::SeedableRng;
-use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
 
-use idolsched::schedule::Schedule;
-use idolsched::accessory::Acc;
+use idolsched::PlayGlob;
+use idolsched::schedule::{Schedule, MoveWeights};
 use idolsched::card::Card;
-use local_search::{SimpleIterSolver, anneal};
+use idolsched::report::Report;
+use idolsched::team::TeamSpec;
+use idolsched::constraint::ConstraintSpec;
+use idolsched::multi::{MultiGlob, Objective, Plan};
+use idolsched::mapdb::FloorType;
+use idolsched::acct_info::AcctInfo;
+use idolsched::advice::{self, Advice, Resource, Upgrade};
+use idolsched::sensitivity::{self, Sensitivity, SlotReport};
+use local_search::{SearchState, ReusableBuffer, SimpleIterSolver, PortableRng, anneal, tempering, parallel, tabu, genetic};
+use local_search::anneal::CoolingKind;
+use local_search::stop::Stop;
+use local_search::memo::MemoStats;
+use local_search::observe;
+use local_search::top::TopK;
+use progress::{ProgressBar, JsonLog, display_memo_stats};
 use card_consumer::Monicker;
+use card_consumer::json_card::TrimCard;
 use card_consumer::network::Cfg as ApiCfg;
 use card_consumer::network::{get_cards, get_images};
+use sifas_data::prelude::Attribute;
+
+// steps between replica exchange attempts when using parallel tempering
+const SWAP_INTERVAL: u32 = 10;
+// steps a card or accessory stays tabu after being moved when polishing
+const TABU_TENURE: u64 = 8;
+// genetic search: survivors per generation, tournament size, chance a child gets a random move
+const ELITE: usize = 2;
+const TOURNAMENT: usize = 3;
+const MUTATION: f64 = 0.3;
 
 // pretty sure there is a way to make Cargo give you this,
 // not that it matters
@@ -36,6 +62,9 @@ async fn main() -> Result<(), error::Error> {
             update_card_data(&api_cfg, &data_path).await?;
         },
         Build(settings) => run_teambuild(settings).await?,
+        BuildMulti(settings, multi) => run_multi(settings, multi).await?,
+        Evaluate(settings, team) => run_evaluate(settings, &team).await?,
+        Scout(settings, scout) => run_scout(settings, scout).await?,
     };
     Ok(())
 }
@@ -53,82 +82,576 @@ enum RunSettings {
         data_path: String,
     },
     Build(TbSettings),
+    BuildMulti(TbSettings, MultiSettings),
+    Evaluate(TbSettings, String),
+    Scout(TbSettings, ScoutSettings),
+}
+
+// cards to try out, whether or not they're in the account
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CardList {
+    All, // every card there is
+    Ordinals(Vec<u32>),
+}
+
+#[derive(Debug, Clone)]
+struct ScoutSettings {
+    cards: CardList,
+    lb: u8,
+    songs: Vec<u32>, // empty for just the --beatmap song
+}
+
+#[derive(Debug, Clone)]
+struct MultiSettings {
+    songs: Vec<u32>,
+    max_uses: Option<usize>,
+    objective: Objective,
+    fatigue_per_use: f64,
 }
 
 #[derive(Debug, Clone)]
 struct TbSettings {
-    step_count: u32,
+    stop: Stop,
     acct_path: PathBuf,
     api_cfg: ApiCfg,
     map_override: Option<u32>,
+    att_override: Option<Attribute>,
+    all_attributes: bool,
+    breakdown: bool,
+    start: Option<String>,
+    refine: bool,
+    constraints_path: Option<PathBuf>,
+    tower: bool,
+    cooling: CoolingKind,
+    auto_t0: bool,
+    save_path: Option<PathBuf>,
+    replicas: usize,
+    population: usize,
+    threads: usize,
+    polish_steps: u32,
+    memo: usize,
+    log_path: Option<PathBuf>,
+    log_every: u64,
+    seed: u64,
+    advise: bool,
+    sensitivity: bool,
+    top: usize,
+    distinct: usize,
+    guest: Option<(u32, u8, bool)>, // ordinal, LB, fed
+    rank_guests: Option<CardList>,
+    rank_as: (u8, bool), // LB, fed
+    moves: MoveWeights,
 }
 
-async fn run_teambuild(settings: TbSettings) -> Result<(), error::Error> {
-    let acct_json = std::fs::read_to_string(settings.acct_path)?;
+// `song_id` is usually main_song(settings), but with --songs it's one of those,
+// so that the --beatmap song (which may not be there) isn't read for nothing
+async fn load_glob(settings: &TbSettings, song_id: u32) -> Result<(PlayGlob, BTreeMap<u32, Monicker>), error::Error> {
+    let (glob, card_names, _, _) = load_account(settings, song_id).await?;
+    Ok((glob, card_names))
+}
+
+// load_glob, plus what it was made from, for things like the upgrade advisor that change the account
+async fn load_account(settings: &TbSettings, song_id: u32)
+-> Result<(PlayGlob, BTreeMap<u32, Monicker>, AcctInfo, BTreeMap<u32, TrimCard>), error::Error> {
+    let acct_json = std::fs::read_to_string(&settings.acct_path)?;
     let acct = idolsched::init_acct(&acct_json)?;
-    let (card_details, card_names) = get_cards::by_ordinal(&settings.api_cfg, acct.card_ordinals()).await?;
-    let song_id = if let Some(v) = settings.map_override {
-        v
-    } else {
-        1_0_015_30_1
-    };
+    let mut ordinals = acct.card_ordinals();
+    ordinals.extend(settings.guest.map(|(ordinal, _, _)| ordinal));
+    let (card_details, card_names) = get_cards::by_ordinal(&settings.api_cfg, ordinals).await?;
     let trimmed_details = card_consumer::trim_cards(&card_details);
     let song_json = std::fs::read_to_string(&format!("./mapdb/{}.json", song_id))?;
-    let glob = idolsched::init_glob(&trimmed_details, &acct, song_id, &song_json)?;
-    let mut rng = SmallRng::from_entropy();
-    let s0 = Schedule::new_random(&mut rng, glob.album.len(), glob.inventory.len());
-    let pm = anneal::Params { rng, t0: 10_000.0, alpha: 1.0 - (1.0/65_536.0) };
-    let mut annealer = anneal::Annealer::org(s0, glob.clone(), pm);
-    let (final_sched, energy) = run_showy(&mut annealer, settings.step_count);
-    println!("Voltage est: {:.1}", -energy);
-    display_sched(&glob.album, &glob.inventory, &final_sched, &card_names);
+    let mut glob = idolsched::init_glob(&trimmed_details, &acct, song_id, &song_json, settings.att_override)?;
+    glob.song.floor_type = floor_type(settings);
+    glob.moves = settings.moves;
+    if let Some((ordinal, lb, fed)) = settings.guest {
+        let guest = match trimmed_details.get(&ordinal) {
+            Some(jc) => Card::instantiate_json(jc, lb, fed),
+            None => return Err(error::Error::StrErr("the --guest card doesn't exist")),
+        };
+        glob.guest = Some(guest);
+    }
+    Ok((glob, card_names, acct, trimmed_details))
+}
+
+fn apply_constraints(glob: PlayGlob, settings: &TbSettings) -> Result<PlayGlob, error::Error> {
+    match settings.constraints_path {
+        Some(ref path) => {
+            let spec = ConstraintSpec::parse(&std::fs::read_to_string(path)?)?;
+            Ok(glob.with_constraints(spec.to_constraints(&glob)?))
+        },
+        None => Ok(glob),
+    }
+}
+
+async fn run_teambuild(settings: TbSettings) -> Result<(), error::Error> {
+    let (glob, card_names, acct, card_details) = load_account(&settings, main_song(&settings)).await?;
+    let glob = apply_constraints(glob, &settings)?;
+    let start = match settings.start {
+        Some(ref team) => Some(load_team(team, &glob)?),
+        None => None,
+    };
+    if let Some(ref team) = start {
+        if !glob.constraints.allows(team) {
+            return Err(error::Error::StrErr("the --start team doesn't follow the constraints"));
+        }
+    }
+    let mut log = match settings.log_path {
+        Some(ref path) => Some(JsonLog::create(path, settings.log_every)?),
+        None => None,
+    };
+    if settings.all_attributes {
+        let mut summary = Vec::new();
+        for (i, &att) in sifas_data::MAIN_ATTRIBUTES.iter().enumerate() {
+            println!("== {:?} ========================", att);
+            let att_glob = glob.with_attribute(att);
+            // each attribute gets its own seed, so `-c<digit> --seed` can redo just that one
+            let seed = settings.seed.wrapping_add(i as u64);
+            let (final_sched, energy) = search_glob(&att_glob, &settings, start, seed, &mut log, &mut TopK::nothing());
+            println!("Voltage est: {:.1}", -energy);
+            println!("Seed: {}", seed);
+            display_sched(&glob, &final_sched, &card_names);
+            if settings.breakdown {
+                let report = Report::from_trace(&att_glob.trace_voltage(&final_sched));
+                display_breakdown(&glob.album, &final_sched, &report, &card_names);
+            }
+            summary.push((att, energy));
+        }
+        println!("== Summary =============");
+        for (att, energy) in summary {
+            println!(" {:<8} {:>12.1}", format!("{:?}", att), -energy);
+        }
+    } else {
+        let mut top = TopK::new(settings.top, settings.distinct, Schedule::cards_differing);
+        let (final_sched, energy) = search_glob(&glob, &settings, start, settings.seed, &mut log, &mut top);
+        println!("Voltage est: {:.1}", -energy);
+        println!("Seed: {}", settings.seed);
+        display_sched(&glob, &final_sched, &card_names);
+        if settings.breakdown {
+            let report = Report::from_trace(&glob.trace_voltage(&final_sched));
+            display_breakdown(&glob.album, &final_sched, &report, &card_names);
+        }
+        let alternatives = top.entries().iter()
+            .filter(|(sched, _)| *sched != final_sched)
+            .take(settings.top - 1);
+        for (i, (sched, alt_energy)) in alternatives.enumerate() {
+            println!("== Alternative {} ===================", i + 2);
+            println!("Voltage est: {:.1} ({:+.1})", -alt_energy, energy - alt_energy);
+            display_sched(&glob, sched, &card_names);
+        }
+        if let Some(ref path) = settings.save_path {
+            let team = TeamSpec::from_schedule(&final_sched, &glob);
+            std::fs::write(path, serde_json::to_string_pretty(&team)?)?;
+        }
+        if settings.sensitivity {
+            display_sensitivity(&glob, &final_sched, &sensitivity::sensitivity(&glob, &final_sched), &card_names);
+        }
+        if let Some(ref guests) = settings.rank_guests {
+            display_guest_ranking(&settings, &glob, &final_sched, guests).await?;
+        }
+        if settings.advise {
+            let advice = advice::advise(&glob, &card_details, &acct, &final_sched);
+            display_advice(&glob, &advice, &card_names);
+        }
+    }
     Ok(())
 }
 
-fn run_showy<Sv: SimpleIterSolver<Schedule>>(solver: &mut Sv, steps: u32)
+// one team per song, all at once; see idolsched::multi
+async fn run_multi(settings: TbSettings, multi: MultiSettings) -> Result<(), error::Error> {
+    let (glob, card_names) = load_glob(&settings, multi.songs[0]).await?;
+    let glob = apply_constraints(glob, &settings)?;
+    let mut songs = Vec::new();
+    for &song_id in multi.songs.iter() {
+        let song_json = std::fs::read_to_string(format!("./mapdb/{}.json", song_id))?;
+        let mut song = idolsched::mapdb::parse_song(song_id, &song_json)?;
+        song.override_attribute(settings.att_override);
+        song.floor_type = floor_type(&settings);
+        songs.push(song);
+    }
+    let mut mglob = MultiGlob::new(&glob, songs, multi.max_uses, multi.objective);
+    mglob.fatigue_per_use = multi.fatigue_per_use;
+    let mut log = match settings.log_path {
+        Some(ref path) => Some(JsonLog::create(path, settings.log_every)?),
+        None => None,
+    };
+
+    // starting plans are found up front, since there might not be one
+    let starts: Option<Vec<(Plan, PortableRng)>> = (0 .. settings.threads.max(1))
+        .map(|run| {
+            let mut rng = PortableRng::seed_from_u64(run_seed(settings.seed, run));
+            Plan::new_random(&mut rng, &mglob).map(|plan| (plan, rng))
+        })
+        .collect();
+    let starts = match starts {
+        Some(starts) => starts,
+        None => return Err(error::Error::StrErr("couldn't find teams for all the songs that follow --reuse and the constraints")),
+    };
+    let found = run_solver(&settings, &mut log, &mut TopK::nothing(), |run| {
+        let (s0, mut rng) = starts[run].clone();
+        let t0 = start_temp(&mut rng, &s0, &mglob, &settings);
+        let pm = anneal::Params { rng, t0, cooling: make_cooling(&settings, t0) };
+        anneal::Annealer::org(s0, mglob.clone(), pm)
+    });
+    let (plan, energy) = climb(&mglob, found);
+
+    let voltages = mglob.voltages(&plan, &mut ReusableBuffer::create());
+    let mut uses = vec![0; glob.album.len()];
+    for ((song_id, floor), (team, voltage)) in multi.songs.iter().zip(mglob.floors.iter())
+        .zip(plan.teams.iter().zip(voltages.iter())) {
+        println!("== Song {} ========================", song_id);
+        println!("Voltage est: {:.1}", voltage);
+        display_sched(floor, team, &card_names);
+        let fatigue = mglob.fatigue(team, &mut uses);
+        if settings.breakdown {
+            let report = Report::from_trace(&floor.trace_voltage_fatigued(team, &fatigue));
+            display_breakdown(&glob.album, team, &report, &card_names);
+        }
+    }
+    println!("== Summary =============");
+    for (song_id, voltage) in multi.songs.iter().zip(voltages.iter()) {
+        println!(" {:<10} {:>12.1}", song_id, voltage);
+    }
+    match multi.objective {
+        Objective::Total => println!(" {:<10} {:>12.1}", "Total", -energy),
+        Objective::Worst => println!(" {:<10} {:>12.1}", "Weakest", -energy),
+    }
+    println!("Seed: {}", settings.seed);
+    Ok(())
+}
+
+// what the cards you don't have would add; see idolsched::scout
+async fn run_scout(settings: TbSettings, scout: ScoutSettings) -> Result<(), error::Error> {
+    const SHOWN_ALL: usize = 20;
+    let song_ids = if scout.songs.is_empty() {
+        vec![main_song(&settings)]
+    } else {
+        scout.songs.clone()
+    };
+    let (glob, card_names) = load_glob(&settings, song_ids[0]).await?;
+    let glob = apply_constraints(glob, &settings)?;
+    let mut log = match settings.log_path {
+        Some(ref path) => Some(JsonLog::create(path, settings.log_every)?),
+        None => None,
+    };
+
+    // each song gets its own team, and its own seed, as with --all-attributes
+    let mut floors = Vec::new();
+    for (i, &song_id) in song_ids.iter().enumerate() {
+        let song_json = std::fs::read_to_string(format!("./mapdb/{}.json", song_id))?;
+        let mut song = idolsched::mapdb::parse_song(song_id, &song_json)?;
+        song.override_attribute(settings.att_override);
+        song.floor_type = floor_type(&settings);
+        let floor = glob.with_song(song);
+        println!("== Song {} ========================", song_id);
+        let seed = settings.seed.wrapping_add(i as u64);
+        let (team, energy) = search_glob(&floor, &settings, None, seed, &mut log, &mut TopK::nothing());
+        println!("Voltage est: {:.1}", -energy);
+        println!("Seed: {}", seed);
+        display_sched(&floor, &team, &card_names);
+        floors.push((floor, team));
+    }
+
+    let (all_details, all_names) = load_card_list(&settings, &scout.cards).await?;
+    let candidates = match pick_cards(&all_details, &scout.cards) {
+        Some(candidates) => candidates,
+        None => return Err(error::Error::StrErr("--scout asked for a card that doesn't exist")),
+    };
+    let prospects = idolsched::scout::scout(&floors, &candidates, scout.lb);
+
+    println!("== Scouting (LB{}) =======", scout.lb);
+    let shown = if scout.cards == CardList::All { SHOWN_ALL } else { prospects.len() };
+    for prospect in prospects.iter().take(shown) {
+        print!(" {:>+10.1} {:>3} {}", prospect.total(), prospect.ordinal, all_names.get(&prospect.ordinal).unwrap());
+        if prospect.gains.len() > 1 {
+            let gains: Vec<String> = prospect.gains.iter().map(|gain| format!("{:+.1}", gain)).collect();
+            print!(" ({})", gains.join(", "));
+        }
+        println!();
+    }
+    if prospects.len() < candidates.len() {
+        println!(" (cards already in the album are left out)");
+    }
+    Ok(())
+}
+
+// what each of `guests`, at the limit break and skill tree settings.rank_as says, would add to `team` as the guest;
+// see idolsched::guest
+async fn display_guest_ranking(settings: &TbSettings, glob: &PlayGlob, team: &Schedule, guests: &CardList)
+-> Result<(), error::Error> {
+    const SHOWN_ALL: usize = 20;
+    let (all_details, all_names) = load_card_list(settings, guests).await?;
+    let candidates = match pick_cards(&all_details, guests) {
+        Some(candidates) => candidates,
+        None => return Err(error::Error::StrErr("--rank-guests asked for a card that doesn't exist")),
+    };
+    let (lb, fed) = settings.rank_as;
+    let ranking = idolsched::guest::rank_guests(glob, team, &candidates, lb, fed);
+    println!("-- Guests (LB{}{}) ---", lb, if fed { ", fed" } else { "" });
+    let shown = if *guests == CardList::All { SHOWN_ALL } else { ranking.len() };
+    for (ordinal, gain) in ranking.iter().take(shown) {
+        println!(" {:>+10.1} {:>3} {}", gain, ordinal, all_names.get(ordinal).unwrap());
+    }
+    Ok(())
+}
+
+// card data for every card in `list`, whether or not it's in the account
+async fn load_card_list(settings: &TbSettings, list: &CardList)
+-> Result<(BTreeMap<u32, TrimCard>, BTreeMap<u32, Monicker>), error::Error> {
+    let (details, names) = match list {
+        CardList::All => get_cards::til_latest(&settings.api_cfg).await?,
+        CardList::Ordinals(ordinals) => get_cards::by_ordinal(&settings.api_cfg, ordinals.clone()).await?,
+    };
+    Ok((card_consumer::trim_cards(&details), names))
+}
+
+// the cards in `list` out of what load_card_list loaded; None if one of them doesn't exist
+fn pick_cards<'a>(details: &'a BTreeMap<u32, TrimCard>, list: &CardList) -> Option<Vec<&'a TrimCard>> {
+    match list {
+        CardList::All => Some(details.values().collect()),
+        CardList::Ordinals(ordinals) => ordinals.iter().map(|ordinal| details.get(ordinal)).collect(),
+    }
+}
+
+// the --beatmap song, or No Exit Orion Adv
+fn main_song(settings: &TbSettings) -> u32 {
+    settings.map_override.unwrap_or(10015301)
+}
+
+fn floor_type(settings: &TbSettings) -> FloorType {
+    if settings.tower {
+        FloorType::Tower
+    } else {
+        FloorType::Free
+    }
+}
+
+async fn run_evaluate(settings: TbSettings, team: &str) -> Result<(), error::Error> {
+    let (glob, card_names, acct, card_details) = load_account(&settings, main_song(&settings)).await?;
+    let sched = load_team(team, &glob)?;
+    let report = Report::from_trace(&glob.trace_voltage(&sched));
+    println!("Voltage est: {:.1}", report.voltage);
+    display_sched(&glob, &sched, &card_names);
+    display_breakdown(&glob.album, &sched, &report, &card_names);
+    if settings.sensitivity {
+        display_sensitivity(&glob, &sched, &sensitivity::sensitivity(&glob, &sched), &card_names);
+    }
+    if let Some(ref guests) = settings.rank_guests {
+        display_guest_ranking(&settings, &glob, &sched, guests).await?;
+    }
+    if settings.advise {
+        let advice = advice::advise(&glob, &card_details, &acct, &sched);
+        display_advice(&glob, &advice, &card_names);
+    }
+    Ok(())
+}
+
+// `team` is the path of a json file, a team code, or the json itself.
+// codes never contain '.', '/' or '\\', so anything else that does is a path, whether or not it exists.
+fn load_team(team: &str, glob: &PlayGlob) -> Result<Schedule, error::Error> {
+    let is_path = Path::new(team).exists()
+        || (!team.trim_start().starts_with('{') && team.contains(['.', '/', '\\']));
+    let team_spec = if is_path {
+        let text = match std::fs::read_to_string(team) {
+            Ok(text) => text,
+            Err(e) => return Err(error::Error::Etc(format!("couldn't read team file {}: {}", team, e).into())),
+        };
+        TeamSpec::parse(&text)?
+    } else {
+        TeamSpec::parse(team)?
+    };
+    Ok(team_spec.to_schedule(glob)?)
+}
+
+// `top` is offered every team the search visits, and the final one
+fn search_glob(
+    glob: &PlayGlob, settings: &TbSettings, start: Option<Schedule>, seed: u64,
+    log: &mut Option<JsonLog>, top: &mut TopK<Schedule>
+) -> (Schedule, f64) {
+    let found = if settings.population > 1 {
+        run_solver(settings, log, top, |run| make_genetic(glob, settings, start, run_seed(seed, run)))
+    } else if settings.replicas > 1 {
+        run_solver(settings, log, top, |run| make_tempering(glob, settings, start, run_seed(seed, run)))
+    } else {
+        run_solver(settings, log, top, |run| make_annealer(glob, settings, start, run_seed(seed, run)))
+    };
+    let found = if settings.polish_steps > 0 {
+        polish(glob, found, settings, seed, log)
+    } else {
+        found
+    };
+    let climbing = glob.for_climbing();
+    let (sched, energy) = climb(&climbing, found);
+    top.offer(&sched, energy);
+    // the other teams kept get climbed too, and one of them can end up ahead
+    *top = top.climbed(&climbing);
+    match top.entries().first() {
+        Some((best, best_energy)) if *best_energy < energy => {
+            println!("A kept alternative climbed past it, adding {:.1} voltage", energy - best_energy);
+            (*best, *best_energy)
+        }
+        _ => (sched, energy),
+    }
+}
+
+// make sure no single move can improve the team, and say so
+fn climb<St: SearchState>(glob: &St::Glob, found: (St, f64)) -> (St, f64) {
+    let (sched, energy, improvements) = local_search::hill_climb(found.0, glob);
+    if improvements == 0 {
+        println!("Local optimum: no single change improves this team");
+    } else {
+        println!("Hill climbing made {} improvement{}, adding {:.1} voltage",
+            improvements, if improvements == 1 { "" } else { "s" }, found.1 - energy);
+    }
+    (sched, energy)
+}
+
+// a short tabu search from the finished team, scanning the whole neighbourhood every step,
+// so that the result is at least a local optimum
+fn polish(glob: &PlayGlob, found: (Schedule, f64), settings: &TbSettings, seed: u64, log: &mut Option<JsonLog>)
 -> (Schedule, f64) {
-    use std::io::Write;
+    let pm = tabu::Params { rng: PortableRng::seed_from_u64(seed), tenure: TABU_TENURE, sample: None };
+    let mut solver = tabu::Tabu::org(found.0, glob.clone(), pm);
+    if settings.memo > 0 {
+        solver.set_memo(settings.memo);
+    }
+    let polished = run_showy(&mut solver, Stop::steps(settings.polish_steps as u64), log, &mut TopK::nothing());
+    if polished.1 < found.1 {
+        println!("Polishing improved voltage by {:.1}", found.1 - polished.1);
+        polished
+    } else {
+        found
+    }
+}
 
-    let one_percent = steps / 100;
-    let mut best: Option<(Schedule, f64)> = None;
+// with --threads, run 0 gets the seed itself, so a single-threaded run with the same seed
+// repeats it. the other runs' seeds are spread out so they can't collide with
+// the consecutive seeds --all-attributes uses.
+fn run_seed(seed: u64, run: usize) -> u64 {
+    seed.wrapping_add((run as u64) << 32)
+}
 
-    let mut step = 0;
-    let mut percent = 0;
-    let mut countdown = 0;
-    while let Some(new) = solver.advance() {
-        if step == steps {
-            break;
+fn run_solver<St, Sv, F>(settings: &TbSettings, log: &mut Option<JsonLog>, top: &mut TopK<St>, make_solver: F)
+-> (St, f64)
+where St: SearchState + Send,
+      Sv: SimpleIterSolver<St>,
+      F: Fn(usize) -> Sv + Sync {
+    let make_solver = |run| {
+        let mut solver = make_solver(run);
+        if settings.memo > 0 {
+            solver.set_memo(settings.memo);
         }
-        step += 1;
+        solver
+    };
+    if settings.threads > 1 {
+        run_threaded(settings, top, make_solver)
+    } else {
+        run_showy(&mut make_solver(0), settings.stop, log, top)
+    }
+}
 
-        if countdown == 0 {
-            if let Some((_, e)) = best {
-                print!("{:>3}% |    {:>12.1}    {:>12.1}\r", percent, -e, -new.1);
+fn make_annealer(glob: &PlayGlob, settings: &TbSettings, start: Option<Schedule>, seed: u64)
+-> anneal::Annealer<Schedule, PortableRng> {
+    let mut rng = PortableRng::seed_from_u64(seed);
+    let s0 = start.unwrap_or_else(|| Schedule::new_random(&mut rng, glob));
+    let t0 = start_temp(&mut rng, &s0, glob, settings);
+    let pm = anneal::Params { rng, t0, cooling: make_cooling(settings, t0) };
+    anneal::Annealer::org(s0, glob.clone(), pm)
+}
+
+fn make_cooling(settings: &TbSettings, t0: f64) -> anneal::Cooling {
+    // get_configuration makes sure there's a step count for the schedules that need one
+    settings.cooling.cooling(settings.stop.steps.unwrap_or(0), t0)
+}
+
+// see --auto-t0 and --refine
+fn start_temp<St: SearchState>(rng: &mut PortableRng, s0: &St, glob: &St::Glob, settings: &TbSettings) -> f64 {
+    anneal::start_temp(rng, s0, glob, settings.auto_t0, settings.refine)
+}
+
+fn make_tempering(glob: &PlayGlob, settings: &TbSettings, start: Option<Schedule>, seed: u64)
+-> tempering::Tempering<Schedule, PortableRng> {
+    let mut rng = PortableRng::seed_from_u64(seed);
+    let s0 = start.unwrap_or_else(|| Schedule::new_random(&mut rng, glob));
+    let t0 = start_temp(&mut rng, &s0, glob, settings);
+    let temps = tempering::ladder(anneal::REFINE_T0.min(t0), t0, settings.replicas);
+    let pm = tempering::Params { rng, temps, swap_interval: SWAP_INTERVAL };
+    tempering::Tempering::org(s0, glob.clone(), pm)
+}
+
+// the population starts out as the --start team (or a random one)
+// plus random teams, or random variations on the --start team
+fn make_genetic(glob: &PlayGlob, settings: &TbSettings, start: Option<Schedule>, seed: u64)
+-> genetic::Genetic<Schedule, PortableRng> {
+    let mut rng = PortableRng::seed_from_u64(seed);
+    let (s0, seeds) = match start {
+        Some(s0) => (s0, Vec::new()),
+        None => {
+            let mut teams: Vec<Schedule> = (0 .. settings.population)
+                .map(|_| Schedule::new_random(&mut rng, glob))
+                .collect();
+            (teams.pop().unwrap(), teams)
+        },
+    };
+    let pm = genetic::Params {
+        rng,
+        size: settings.population,
+        seeds,
+        elite: ELITE,
+        tournament: TOURNAMENT,
+        mutation: MUTATION,
+    };
+    genetic::Genetic::org(s0, glob.clone(), pm)
+}
+
+// one independent search per thread, each with its own random start (unless --start was given)
+fn run_threaded<St, Sv, F>(settings: &TbSettings, top: &mut TopK<St>, make_solver: F) -> (St, f64)
+where St: SearchState + Send,
+      Sv: SimpleIterSolver<St>,
+      F: Fn(usize) -> Sv + Sync {
+    use std::io::Write;
+
+    let stop = settings.stop;
+    let threads = settings.threads as u64;
+    let started = Instant::now();
+    let result = parallel::search_parallel(settings.threads, settings.threads, stop, top.empty_like(), make_solver,
+        |steps, best| {
+            let by_steps = stop.steps.map_or(0.0, |n| steps as f64 / (n * threads).max(1) as f64);
+            let by_time = stop.time.map_or(0.0, |t| started.elapsed().as_secs_f64() / t.as_secs_f64());
+            let percent = (100.0 * by_steps.max(by_time).min(1.0)) as u32;
+            if let Some(e) = best {
+                print!("{:>3}% |    {:>12.1}\r", percent, -e);
             } else {
                 print!("{:>3}%\r", percent);
             }
             std::io::stdout().flush().unwrap();
-            countdown = one_percent;
-            percent += 1;
-        } else {
-            countdown -= 1;
         }
+    );
 
-        if let Some(ref v) = best {
-            if new.1 < v.1 {
-                best = Some(new);
-            }
-        } else {
-            best = Some(new);
-        }
+    print!("                                                   \r");
+    for (i, stats) in result.threads.iter().enumerate() {
+        let best = stats.best.map(|e| format!("{:.1}", -e)).unwrap_or_else(|| "-".to_string());
+        println!("thread {:>2}: {:>9} steps, best {:>12}", i, stats.steps, best);
+    }
+    let memo = result.threads.iter()
+        .filter_map(|stats| stats.memo)
+        .fold(None, |acc: Option<MemoStats>, m| Some(acc.map_or(m, |a| a.combine(&m))));
+    if let Some(ref memo) = memo {
+        display_memo_stats(memo);
     }
 
-    print!("                                                   \r");
+    top.merge(result.top);
+    result.best.unwrap()
+}
 
+fn run_showy<St: SearchState, Sv: SimpleIterSolver<St>>(
+    solver: &mut Sv, stop: Stop, log: &mut Option<JsonLog>, top: &mut TopK<St>
+) -> (St, f64) {
+    let (best, _) = observe::search_observed_top(solver, stop, &mut (ProgressBar::new(), log), top);
     best.unwrap()
 }
 
-fn display_sched(album: &Vec<Card>, inv: &Vec<Acc>, sched: &Schedule, monickers: &BTreeMap<u32, Monicker>) {
+fn display_sched(glob: &PlayGlob, sched: &Schedule, monickers: &BTreeMap<u32, Monicker>) {
+    let (album, inv) = (&glob.album, &glob.inventory);
     for (i, card_i) in sched.cards.iter().enumerate() {
         if i == 0 {
             println!("-- Green ---------------");
@@ -157,6 +680,102 @@ fn display_sched(album: &Vec<Card>, inv: &Vec<Acc>, sched: &Schedule, monickers:
             println!("");
         }
     }
+    println!("Team code: {}", TeamSpec::from_schedule(sched, glob).to_code());
+}
+
+// for each slot, the voltage lost (or gained) by putting the best and the worst replacement in it
+fn display_sensitivity(glob: &PlayGlob, sched: &Schedule, sens: &Sensitivity, monickers: &BTreeMap<u32, Monicker>) {
+    const SLOTS: [&str; 9] = ["G1", "G2", "G3", "B1", "B2", "B3", "R1", "R2", "R3"];
+    let card_name = |card_i: usize| {
+        let card = &glob.album[card_i];
+        format!("{:>3} {}", card.ordinal, monickers.get(&card.ordinal).unwrap())
+    };
+    let acc_name = |acc_i: usize| format!("{} ({})", glob.inventory[acc_i].name(), acc_i);
+    let replacement = |slot: &SlotReport, worst: bool, name: &dyn Fn(usize) -> String| {
+        let found = if worst { slot.worst } else { slot.best };
+        match found {
+            Some((i, delta)) => format!("{:>+10.1} {:<16}", delta, name(i)),
+            None => format!("{:>10} {:<16}", "-", "(can't change)"),
+        }
+    };
+    println!("-- Sensitivity ---------");
+    println!(" {:<4} {:<16} {:>27} {:>27}", "slot", "card", "best replacement", "worst replacement");
+    for (i, slot) in sens.cards.iter().enumerate() {
+        println!(" {:<4} {:<16} {} {}", SLOTS[i], card_name(sched.cards[i]),
+            replacement(slot, false, &card_name), replacement(slot, true, &card_name));
+    }
+    println!(" {:<4} {:<16} {:>27} {:>27}", "slot", "accessory", "best replacement", "worst replacement");
+    for (i, slot) in sens.accs.iter().enumerate() {
+        println!(" {:<4} {:<16} {} {}", SLOTS[i], acc_name(sched.accs[i]),
+            replacement(slot, false, &acc_name), replacement(slot, true, &acc_name));
+    }
+}
+
+// the best few upgrades for each resource; `advice` is grouped by resource, as advice::advise returns it
+fn display_advice(glob: &PlayGlob, advice: &[Advice], monickers: &BTreeMap<u32, Monicker>) {
+    const SHOWN: usize = 5;
+    if advice.is_empty() {
+        println!("-- Upgrades ------------");
+        println!(" nothing the account could upgrade would help this team");
+        return;
+    }
+    let mut shown = 0;
+    for (i, adv) in advice.iter().enumerate() {
+        if i == 0 || advice[i - 1].resource != adv.resource {
+            let heading = match adv.resource {
+                Resource::Lb(rarity) => format!("{} limit breaks", format!("{:?}", rarity).to_uppercase()),
+                Resource::Training(rarity) => format!("{} skill trees", format!("{:?}", rarity).to_uppercase()),
+                Resource::AccLevels => "Accessory levels".to_string(),
+            };
+            println!("-- {} ----------", heading);
+            shown = 0;
+        }
+        if shown == SHOWN {
+            continue;
+        }
+        shown += 1;
+        match adv.upgrade {
+            Upgrade::CardLb(ordinal) | Upgrade::Feed(ordinal) => {
+                println!(" {:>+10.1} {:>3} {}", adv.gain, ordinal, monickers.get(&ordinal).unwrap());
+            },
+            Upgrade::AccLevel(acc_i, lv) => {
+                println!(" {:>+10.1} {} ({}) to Lv{}, {:.1} per level",
+                    adv.gain, glob.inventory[acc_i].name(), acc_i, lv, adv.gain_per_unit()
+                );
+            },
+        }
+    }
+}
+
+fn display_breakdown(album: &[Card], sched: &Schedule, report: &Report, monickers: &BTreeMap<u32, Monicker>) {
+    println!("-- Breakdown -----------");
+    println!(" {:<16} {:>5} {:>6} {:>10} {:>10} {:>10} {:>5} {:>8} {:>8}",
+        "card", "notes", "buffed", "appeal vo", "crit vo", "skill vo", "caps", "heal", "shield");
+    for (i, c) in report.by_card.iter().enumerate() {
+        let card = &album[sched.cards[i]];
+        let name = format!("{:>3} {}", card.ordinal, monickers.get(&card.ordinal).unwrap());
+        println!(" {:<16} {:>5} {:>6} {:>10.1} {:>10.1} {:>10.1} {:>5} {:>8.1} {:>8.1}",
+            name, c.notes, c.buffed_notes, c.appeal_voltage(), c.crit, c.skill,
+            c.tap_caps + c.skill_caps, c.heal, c.shield);
+    }
+    println!("-- By strategy ---------");
+    for (name, c) in ["Green", "Blue", "Red"].iter().zip(report.by_strat.iter()) {
+        println!(" {:<6} {:>5} notes {:>12.1} voltage {:>10.1} damage taken", name, c.notes, c.voltage(), c.damage);
+    }
+    println!("-- By source -----------");
+    let total = report.total.voltage().max(1.0);
+    let sources = [
+        ("appeal", report.total.appeal_voltage()),
+        ("crit", report.total.crit),
+        ("skill", report.total.skill),
+    ];
+    for (name, v) in sources.iter() {
+        println!(" {:<6} {:>12.1} ({:>4.1}%)", name, v, 100.0 * v / total);
+    }
+    println!(" stamina {:.0} / {:.0}", report.final_stamina, report.max_stamina);
+    if let Some(note) = report.died_at {
+        println!(" died at note {} with {:.1} voltage", note, report.voltage);
+    }
 }
 
 // this function (as well as get_cfg) blocks on I/O bc we can't even start other I/O
@@ -168,7 +787,7 @@ fn get_configuration() -> Result<RunSettings, error::Error> {
         "number of steps for the annealer.\n\
         needs enormous changes to see much difference;\n\
         if you are having poor results, trying adding 0s.\n\
-        defaults to 10000 if unspecified.",
+        defaults to 10000 if unspecified, or no limit if --time is given.",
         "STEPS"
     );
     opts.optopt("", "api-cfg",
@@ -182,10 +801,219 @@ fn get_configuration() -> Result<RunSettings, error::Error> {
         "FILE"
     );
     opts.optopt("m", "beatmap",
+        "song ID of the beatmap to use, which is loaded from './mapdb/ID.json'.\n\
+        defaults to 10015301 (No Exit Orion Adv) if unspecified.",
+        "ID"
+    );
+    opts.optopt("c", "attribute",
         "attribute override. if absent, the song's default attribute is used; if present,\n\
         attribute is replaced with 0123456 = XSPCANE,\n\
         where X is neutral, and SPCANE are the six main attributes. e.g.:\n\
         --attribute=4 chooses Active",
+        "N"
+    );
+    opts.optopt("", "songs",
+        "build one team for each of a list of songs (comma-separated IDs,\n\
+        as for --beatmap) all at once, out of the same cards and accessories,\n\
+        e.g. for the floors of a tower. can't be combined with --all-attributes,\n\
+        --start, --save, --replicas, --population or --polish.",
+        "ID,ID,..."
+    );
+    opts.optopt("", "reuse",
+        "with --songs, put each card on at most N of the teams.\n\
+        defaults to no limit.",
+        "N"
+    );
+    opts.optopt("", "objective",
+        "with --songs, what makes a set of teams good: total (the default)\n\
+        for the most voltage over all the songs, or worst for the most voltage\n\
+        on the weakest song, when all of them have to be cleared.",
+        "total|worst"
+    );
+    opts.optflag("", "tower",
+        "treat the song (or every song given to --songs) as a tower floor,\n\
+        where cards lose the appeal given by \"fatigue\" in the account file."
+    );
+    opts.optopt("", "fatigue",
+        "with --songs and --tower, the percent of appeal a card loses\n\
+        for each earlier song it's used on. defaults to 0.",
+        "PCT"
+    );
+    opts.optflag("", "all-attributes",
+        "build a team for each of the six main attributes in turn,\n\
+        as if the song had that attribute, and show all six results."
+    );
+    opts.optflag("", "breakdown",
+        "after building a team, show where its voltage comes from:\n\
+        per card, per strategy, and appeal vs. crits vs. skills."
+    );
+    opts.optflag("", "advise",
+        "after building (or with --evaluate, scoring) a team, try every limit break,\n\
+        skill tree and accessory level the account could still get, re-tune the team\n\
+        around each, and list the ones worth the most voltage."
+    );
+    opts.optflag("", "sensitivity",
+        "after building (or with --evaluate, scoring) a team, replace each card\n\
+        and accessory with the best and the worst thing that could take its place\n\
+        and show how much voltage that changes, to see which slots matter."
+    );
+    opts.optopt("", "top",
+        "after the best team, also show the next best K-1 teams the search came across,\n\
+        each differing from the ones above it by at least --distinct cards.\n\
+        defaults to 1.",
+        "K"
+    );
+    opts.optopt("", "distinct",
+        "with --top, how many cards a team needs that the better ones don't have\n\
+        to count as a different team. defaults to 2.",
+        "N"
+    );
+    opts.optopt("", "guest",
+        "play with a guest card, whose passive skills apply to the team:\n\
+        its ordinal, then optionally its limit break and \"fed\" if its skill tree\n\
+        is done, e.g. 123,5,fed. defaults to LB0 and not fed.",
+        "ORD[,LB[,fed]]"
+    );
+    opts.optopt("", "rank-guests",
+        "after building (or with --evaluate, scoring) a team, show how much voltage\n\
+        each of a list of cards (comma-separated ordinals) would add as the guest,\n\
+        at the limit break --rank-as gives. \"all\" tries every card there is\n\
+        and shows the top 20.",
+        "ORD,ORD,...|all"
+    );
+    opts.optopt("", "rank-as",
+        "the limit break, and \"fed\" if the skill tree is done, that --rank-guests\n\
+        tries each card at, e.g. 3 or 5,fed. defaults to 5,fed.",
+        "LB[,fed]"
+    );
+    opts.optopt("", "evaluate",
+        "instead of building a team, score TEAM, which is a team code or a file.\n\
+        a team file is json like {\"cards\": [...], \"accs\": [...], \"sp3\": [...]},\n\
+        where cards are 9 card ordinals, accs are 9 indexes into your account's\n\
+        accessory list (or accessories written out as in account.json), and sp3 are the 3 team positions (0-8) doing SP, center first.\n\
+        positions are in the order green, blue, red.",
+        "TEAM"
+    );
+    opts.optopt("", "scout",
+        "instead of one team, build the best team for the song (or for each song\n\
+        given to --songs, separately), then try adding each of a list of cards\n\
+        you don't have (comma-separated ordinals, e.g. a banner's cards) and show\n\
+        how much voltage each would add. \"all\" tries every card there is\n\
+        and shows the top 20.",
+        "ORD,ORD,...|all"
+    );
+    opts.optopt("", "scout-lb",
+        "with --scout, the limit break the new cards are tried at. defaults to 0.",
+        "N"
+    );
+    opts.optopt("s", "start",
+        "start searching from TEAM (same format as --evaluate)\n\
+        instead of from a random team.",
+        "TEAM"
+    );
+    opts.optflag("r", "refine",
+        "start the annealer at a low temperature, so that it polishes\n\
+        the starting team instead of wandering away from it.\n\
+        mostly useful along with --start."
+    );
+    opts.optopt("", "constraints",
+        "only build teams that follow the rules in FILE: cards locked into\n\
+        positions, cards that must or mustn't be used, banned accessories,\n\
+        and how many cards must come from a school, subunit or attribute.\n\
+        see the README for the format.",
+        "FILE"
+    );
+    opts.optopt("", "moves",
+        "how often the search tries each kind of move, e.g. back=1,formation=2.\n\
+        kinds are green (swap a green card with another), card (bring in a new card),\n\
+        acc (bring in a new accessory), back (swap a blue card with a red one),\n\
+        formation (swap two cards in the same strategy), strategy (swap two\n\
+        whole strategies) and acc-swap (swap accessories between strategies).\n\
+        0 turns a kind off, and weights go up to 65535. defaults to green=1,card=1,acc=1\n\
+        and the rest 0. the hill climb at the end always tries green, card and acc moves.",
+        "KIND=W,..."
+    );
+    opts.optopt("", "cooling",
+        "how the annealer's temperature falls: geometric (the default),\n\
+        linear, target (geometric, ending at a low temperature when the steps run out),\n\
+        reheat (target, but starting over a few times), or lam\n\
+        (steers the temperature to keep a set share of moves accepted).\n\
+        all but geometric need a step count, so they don't go with --time alone.",
+        "KIND"
+    );
+    opts.optflag("", "auto-t0",
+        "pick the starting temperature by trying some random moves,\n\
+        instead of using a fixed one. affects --replicas too."
+    );
+    opts.optopt("t", "time",
+        "stop searching after SECS seconds (fractions are fine).",
+        "SECS"
+    );
+    opts.optopt("", "stall",
+        "stop searching after N steps in a row that don't find a better team.",
+        "N"
+    );
+    opts.optopt("", "target",
+        "stop searching as soon as a team reaches VOLTAGE.",
+        "VOLTAGE"
+    );
+    opts.optopt("", "min-temp",
+        "stop annealing once the temperature has cooled to T.\n\
+        can't be combined with --replicas or --population.",
+        "T"
+    );
+    opts.optopt("", "replicas",
+        "search with parallel tempering, using K chains at temperatures\n\
+        spread between cold and hot, instead of plain annealing.\n\
+        each step costs K times as much, so it is worth lowering -n.\n\
+        values below 2 mean plain annealing, which is the default.",
+        "K"
+    );
+    opts.optopt("", "population",
+        "search with a genetic algorithm over N teams instead of annealing,\n\
+        mixing strategies, accessory sets and SP trios of good teams.\n\
+        each step is a generation and costs about N times as much, so lower -n.\n\
+        can't be combined with --replicas or --min-temp.",
+        "N"
+    );
+    opts.optopt("j", "threads",
+        "run N independent searches at once on N threads and keep the best.\n\
+        each search gets the full step count. defaults to 1.",
+        "N"
+    );
+    opts.optopt("p", "polish",
+        "after the search, run STEPS steps of tabu search from the result.\n\
+        each step tries every possible change to the team, so this is slow,\n\
+        but given a few steps it makes sure no single change can improve the team.\n\
+        defaults to 0 (no polishing).",
+        "STEPS"
+    );
+    opts.optopt("", "memo",
+        "remember the voltage of up to N teams already tried, so they\n\
+        aren't simulated again, and print how often that helped.\n\
+        mostly worth it with --polish or --population. defaults to 0 (off).",
+        "N"
+    );
+    opts.optopt("", "log",
+        "write the search's progress to FILE as json lines, for plotting.\n\
+        each line has the step, temperature, current and best energy\n\
+        (negative voltage), acceptance rate and cache stats, where they apply.\n\
+        can't be combined with --threads.",
+        "FILE"
+    );
+    opts.optopt("", "log-every",
+        "with --log, write a line every N steps. defaults to 100.",
+        "N"
+    );
+    opts.optopt("", "seed",
+        "seed the random number generator with N, a number printed with every result.\n\
+        the same seed and options give the same team again, on any machine,\n\
+        unless the search is stopped by --time. defaults to a random seed.",
+        "N"
+    );
+    opts.optopt("", "save",
+        "save the finished team to FILE, for use with --start or --evaluate.\n\
+        can't be combined with --all-attributes.",
         "FILE"
     );
     opts.optflag("", "version", "print version information and exit immediately");
@@ -223,14 +1051,288 @@ fn get_configuration() -> Result<RunSettings, error::Error> {
 
     let map_override = matches.opt_str("beatmap").map(|s| s.parse().unwrap());
 
+    let att_override = match matches.opt_str("attribute") {
+        Some(s) => match s.parse().ok().and_then(Attribute::from_digit) {
+            Some(att) => Some(att),
+            None => return Err(error::Error::StrErr("attribute override must be a digit from 0 to 6")),
+        },
+        None => None,
+    };
+    let all_attributes = matches.opt_present("all-attributes");
+    let breakdown = matches.opt_present("breakdown");
+    let advise = matches.opt_present("advise");
+    let sensitivity = matches.opt_present("sensitivity");
+    if (advise || sensitivity) && all_attributes {
+        return Err(error::Error::StrErr("--advise and --sensitivity can't be used with --all-attributes"));
+    }
+    let start = matches.opt_str("start");
+    let save_path = matches.opt_str("save").map(PathBuf::from);
+    if save_path.is_some() && all_attributes {
+        return Err(error::Error::StrErr("--save can't be used with --all-attributes"));
+    }
+    let refine = matches.opt_present("refine");
+    let constraints_path = matches.opt_str("constraints").map(PathBuf::from);
+    let tower = matches.opt_present("tower");
+    let auto_t0 = matches.opt_present("auto-t0");
+    let cooling = match matches.opt_str("cooling") {
+        Some(name) => match CoolingKind::parse(&name) {
+            Some(cooling) => cooling,
+            None => return Err(error::Error::StrErr("--cooling must be geometric, linear, target, reheat or lam")),
+        },
+        None => CoolingKind::Geometric,
+    };
+
     let acct_path = PathBuf::from(matches.opt_str("account").unwrap_or_else(|| "account.json".to_string()));
 
-    let step_count: u32 = match matches.opt_get_default("n", 10000) {
+    let time: Option<f64> = match matches.opt_get("time") {
+        Ok(v) => v,
+        Err(e) => return Err(error::Error::Etc(Box::new(e))),
+    };
+    let time = match time {
+        Some(secs) if secs.is_finite() && secs > 0.0 => Some(Duration::from_secs_f64(secs)),
+        Some(_) => return Err(error::Error::StrErr("--time must be a positive number of seconds")),
+        None => None,
+    };
+    let steps: Option<u64> = match matches.opt_get("n") {
+        Ok(v) => v,
+        Err(e) => return Err(error::Error::Etc(Box::new(e))),
+    };
+    if steps == Some(0) {
+        return Err(error::Error::StrErr("-n must be at least 1"));
+    }
+    let steps = match (steps, time) {
+        (None, None) => Some(10000),
+        (steps, _) => steps,
+    };
+    let stall: Option<u64> = match matches.opt_get("stall") {
+        Ok(v) => v,
+        Err(e) => return Err(error::Error::Etc(Box::new(e))),
+    };
+    let target: Option<f64> = match matches.opt_get("target") {
+        Ok(v) => v,
+        Err(e) => return Err(error::Error::Etc(Box::new(e))),
+    };
+    let min_temp: Option<f64> = match matches.opt_get("min-temp") {
+        Ok(v) => v,
+        Err(e) => return Err(error::Error::Etc(Box::new(e))),
+    };
+    if cooling != CoolingKind::Geometric && steps.is_none() {
+        return Err(error::Error::StrErr("--cooling other than geometric needs a step count (-n)"));
+    }
+    // energy is negative voltage
+    let stop = Stop { steps, time, stall, target: target.map(|v| -v), min_temp };
+
+    let replicas: usize = match matches.opt_get_default("replicas", 1) {
+        Ok(v) => v,
+        Err(e) => return Err(error::Error::Etc(Box::new(e))),
+    };
+
+    let population: usize = match matches.opt_get_default("population", 1) {
+        Ok(v) => v,
+        Err(e) => return Err(error::Error::Etc(Box::new(e))),
+    };
+    if population > 1 && replicas > 1 {
+        return Err(error::Error::StrErr("--population and --replicas can't be used together"));
+    }
+    // tempering never cools and the genetic search has no temperature, so there'd be nothing to stop at
+    if min_temp.is_some() && (replicas > 1 || population > 1) {
+        return Err(error::Error::StrErr("--min-temp can't be used with --replicas or --population"));
+    }
+
+    let threads: usize = match matches.opt_get_default("threads", 1) {
+        Ok(v) => v,
+        Err(e) => return Err(error::Error::Etc(Box::new(e))),
+    };
+
+    let polish_steps: u32 = match matches.opt_get_default("polish", 0) {
+        Ok(v) => v,
+        Err(e) => return Err(error::Error::Etc(Box::new(e))),
+    };
+
+    let memo: usize = match matches.opt_get_default("memo", 0) {
+        Ok(v) => v,
+        Err(e) => return Err(error::Error::Etc(Box::new(e))),
+    };
+
+    let log_path = matches.opt_str("log").map(PathBuf::from);
+    let log_every: u64 = match matches.opt_get_default("log-every", 100) {
         Ok(v) => v,
         Err(e) => return Err(error::Error::Etc(Box::new(e))),
     };
+    if log_path.is_some() && threads > 1 {
+        return Err(error::Error::StrErr("--log can't be used with --threads"));
+    }
 
-    Ok(Build(TbSettings { step_count, acct_path, api_cfg, map_override }))
+    let top: usize = match matches.opt_get_default("top", 1) {
+        Ok(v) => v,
+        Err(e) => return Err(error::Error::Etc(Box::new(e))),
+    };
+    let distinct: usize = match matches.opt_get_default("distinct", 2) {
+        Ok(v) => v,
+        Err(e) => return Err(error::Error::Etc(Box::new(e))),
+    };
+    if top == 0 || distinct == 0 || distinct > 9 {
+        return Err(error::Error::StrErr("--top must be at least 1, and --distinct from 1 to 9"));
+    }
+    if top > 1 && all_attributes {
+        return Err(error::Error::StrErr("--top can't be used with --all-attributes"));
+    }
+
+    let moves = match matches.opt_str("moves") {
+        Some(text) => match MoveWeights::parse(&text) {
+            Some(moves) => moves,
+            None => return Err(error::Error::StrErr(
+                "--moves must be a comma-separated list of KIND=WEIGHT (0 to 65535), with at least one weight above 0"
+            )),
+        },
+        None => MoveWeights::default(),
+    };
+
+    let guest = match matches.opt_str("guest") {
+        Some(text) => match parse_guest(&text) {
+            Some(guest) => Some(guest),
+            None => return Err(error::Error::StrErr("--guest must be an ordinal, optionally followed by ,LB (0 to 5) and ,fed")),
+        },
+        None => None,
+    };
+    let rank_guests = match matches.opt_str("rank-guests") {
+        Some(text) => match parse_card_list(&text) {
+            Some(guests) => Some(guests),
+            None => return Err(error::Error::StrErr("--rank-guests must be a comma-separated list of card ordinals, or all")),
+        },
+        None => None,
+    };
+    if rank_guests.is_some() && all_attributes {
+        return Err(error::Error::StrErr("--rank-guests can't be used with --all-attributes"));
+    }
+    let rank_as = match matches.opt_str("rank-as") {
+        Some(_) if rank_guests.is_none() => return Err(error::Error::StrErr("--rank-as needs --rank-guests")),
+        Some(text) => match parse_lb_fed(&text) {
+            Some(rank_as) => rank_as,
+            None => return Err(error::Error::StrErr("--rank-as must be a limit break (0 to 5), optionally followed by ,fed")),
+        },
+        None => (5, true),
+    };
+
+    let seed: u64 = match matches.opt_get("seed") {
+        Ok(v) => v.unwrap_or_else(|| rand::thread_rng().gen()),
+        Err(e) => return Err(error::Error::Etc(Box::new(e))),
+    };
+
+    let settings = TbSettings {
+        stop, acct_path, api_cfg, map_override, att_override,
+        all_attributes, breakdown, start, refine, constraints_path, tower, cooling, auto_t0, save_path,
+        replicas, population, threads, polish_steps, memo, log_path, log_every, seed, advise, sensitivity, top, distinct,
+        guest, rank_guests, rank_as, moves,
+    };
+    let songs = match matches.opt_str("songs") {
+        Some(songs) => match parse_ids(&songs) {
+            Some(songs) => Some(songs),
+            None => return Err(error::Error::StrErr("--songs must be a comma-separated list of song IDs")),
+        },
+        None => None,
+    };
+    // --songs on its own builds a plan; with --scout it only picks the songs to scout for
+    let plan = songs.is_some() && !matches.opt_present("evaluate") && !matches.opt_present("scout");
+    if matches.opt_present("fatigue") && !(plan && settings.tower) {
+        return Err(error::Error::StrErr("--fatigue only works with both --songs and --tower"));
+    }
+    if (matches.opt_present("reuse") || matches.opt_present("objective")) && !plan {
+        return Err(error::Error::StrErr("--reuse and --objective only work with --songs"));
+    }
+    if let Some(team) = matches.opt_str("evaluate") {
+        Ok(Evaluate(settings, team))
+    } else if let Some(cards) = matches.opt_str("scout") {
+        let cards = match parse_card_list(&cards) {
+            Some(cards) => cards,
+            None => return Err(error::Error::StrErr("--scout must be a comma-separated list of card ordinals, or all")),
+        };
+        let lb: u8 = match matches.opt_get_default("scout-lb", 0) {
+            Ok(v) => v,
+            Err(e) => return Err(error::Error::Etc(Box::new(e))),
+        };
+        if lb > 5 {
+            return Err(error::Error::StrErr("--scout-lb must be from 0 to 5"));
+        }
+        if settings.all_attributes || settings.start.is_some() || settings.save_path.is_some()
+        || settings.advise || settings.sensitivity || settings.top > 1 || settings.rank_guests.is_some() {
+            return Err(error::Error::StrErr(
+                "--scout can't be used with --all-attributes, --start, --save, --advise, --sensitivity, --top or --rank-guests"
+            ));
+        }
+        Ok(Scout(settings, ScoutSettings { cards, lb, songs: songs.unwrap_or_default() }))
+    } else if let Some(songs) = songs {
+        if settings.all_attributes || settings.start.is_some() || settings.save_path.is_some()
+        || settings.replicas > 1 || settings.population > 1 || settings.polish_steps > 0 {
+            return Err(error::Error::StrErr(
+                "--songs can't be used with --all-attributes, --start, --save, --replicas, --population or --polish"
+            ));
+        }
+        if settings.advise || settings.sensitivity || settings.top > 1 || settings.rank_guests.is_some() {
+            return Err(error::Error::StrErr("--advise, --sensitivity, --top and --rank-guests can't be used with --songs"));
+        }
+        let max_uses: Option<usize> = match matches.opt_get("reuse") {
+            Ok(v) => v,
+            Err(e) => return Err(error::Error::Etc(Box::new(e))),
+        };
+        if max_uses == Some(0) {
+            return Err(error::Error::StrErr("--reuse must be at least 1"));
+        }
+        let objective = match matches.opt_str("objective").as_deref() {
+            None | Some("total") => Objective::Total,
+            Some("worst") => Objective::Worst,
+            Some(_) => return Err(error::Error::StrErr("--objective must be total or worst")),
+        };
+        let fatigue: f64 = match matches.opt_get_default("fatigue", 0.0) {
+            Ok(v) => v,
+            Err(e) => return Err(error::Error::Etc(Box::new(e))),
+        };
+        if !(0.0 ..= 100.0).contains(&fatigue) {
+            return Err(error::Error::StrErr("--fatigue must be a percentage from 0 to 100"));
+        }
+        let fatigue_per_use = fatigue / 100.0;
+        Ok(BuildMulti(settings, MultiSettings { songs, max_uses, objective, fatigue_per_use }))
+    } else {
+        Ok(Build(settings))
+    }
+}
+
+// a comma-separated list of song IDs or card ordinals
+fn parse_ids(list: &str) -> Option<Vec<u32>> {
+    let ids: Result<Vec<u32>, _> = list.split(',').map(|id| id.trim().parse()).collect();
+    ids.ok().filter(|ids| !ids.is_empty())
+}
+
+// card ordinals as for parse_ids, or "all"
+fn parse_card_list(list: &str) -> Option<CardList> {
+    match list {
+        "all" => Some(CardList::All),
+        list => parse_ids(list).map(CardList::Ordinals),
+    }
+}
+
+// ORD, ORD,LB or ORD,LB,fed
+fn parse_guest(text: &str) -> Option<(u32, u8, bool)> {
+    let (ordinal, (lb, fed)) = match text.split_once(',') {
+        Some((ordinal, rest)) => (ordinal, parse_lb_fed(rest)?),
+        None => (text, (0, false)),
+    };
+    Some((ordinal.trim().parse().ok()?, lb, fed))
+}
+
+// LB or LB,fed
+fn parse_lb_fed(text: &str) -> Option<(u8, bool)> {
+    let mut parts = text.split(',').map(str::trim);
+    let lb = parts.next()?.parse().ok().filter(|&lb| lb <= 5)?;
+    let fed = match parts.next() {
+        Some("fed") => true,
+        Some(_) => return None,
+        None => false,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((lb, fed))
 }
 
 fn get_cfg(path: &str) -> Result<ApiCfg, error::Error> {
@@ -0,0 +1,125 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+use serde::Serialize;
+
+use local_search::memo::MemoStats;
+use local_search::observe::{Observer, Progress};
+use local_search::stop::StopReason;
+
+// the carriage-return progress bar: percent done, best voltage, current voltage
+pub struct ProgressBar {
+    shown: Option<u32>,
+}
+
+impl ProgressBar {
+    pub fn new() -> ProgressBar {
+        ProgressBar { shown: None }
+    }
+}
+
+impl Observer for ProgressBar {
+    fn observe(&mut self, p: &Progress) {
+        let percent = (100.0 * p.fraction.unwrap_or(0.0)) as u32;
+        if self.shown != Some(percent) {
+            print!("{:>3}% |    {:>12.1}    {:>12.1}\r", percent, -p.best, -p.current);
+            std::io::stdout().flush().unwrap();
+            self.shown = Some(percent);
+        }
+    }
+
+    fn finish(&mut self, p: &Progress, reason: StopReason) {
+        print!("                                                   \r");
+        let why = match reason {
+            StopReason::Steps => None,
+            StopReason::Time => Some("out of time"),
+            StopReason::Stall => Some("no recent improvement"),
+            StopReason::Target => Some("reached target voltage"),
+            StopReason::MinTemp => Some("cooled to minimum temperature"),
+            StopReason::Exhausted => Some("nothing left to try"),
+        };
+        if let Some(why) = why {
+            println!("Stopped after {} steps: {}", p.step, why);
+        }
+        if let Some(ref memo) = p.memo {
+            display_memo_stats(memo);
+        }
+    }
+}
+
+pub fn display_memo_stats(stats: &MemoStats) {
+    println!("Cache: {:.1}% hits ({} of {} lookups, {} of {} entries used)",
+        100.0 * stats.hit_rate(), stats.hits, stats.hits + stats.misses, stats.len, stats.capacity);
+}
+
+// --log: a line of json every so often, for plotting how searches went.
+// energies are negative voltage.
+pub struct JsonLog {
+    out: BufWriter<File>,
+    every: u64,
+    countdown: u64,
+    search: usize, // counts up each time a search finishes
+    started: Instant,
+    failed: bool,
+}
+
+#[derive(Serialize)]
+struct LogLine<'a> {
+    search: usize,
+    seconds: f64,
+    #[serde(flatten)]
+    progress: &'a Progress,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stopped: Option<String>,
+}
+
+impl JsonLog {
+    pub fn create(path: &Path, every: u64) -> std::io::Result<JsonLog> {
+        Ok(JsonLog {
+            out: BufWriter::new(File::create(path)?),
+            every: every.max(1),
+            countdown: 0,
+            search: 0,
+            started: Instant::now(),
+            failed: false,
+        })
+    }
+
+    fn write(&mut self, progress: &Progress, stopped: Option<String>) {
+        let line = LogLine {
+            search: self.search,
+            seconds: self.started.elapsed().as_secs_f64(),
+            progress,
+            stopped,
+        };
+        let written = writeln!(self.out, "{}", serde_json::to_string(&line).unwrap());
+        if let Err(e) = written {
+            if !self.failed {
+                eprintln!("couldn't write to log: {}", e);
+                self.failed = true;
+            }
+        }
+    }
+}
+
+impl Observer for JsonLog {
+    fn observe(&mut self, progress: &Progress) {
+        if self.countdown == 0 {
+            self.write(progress, None);
+            self.countdown = self.every;
+        }
+        self.countdown -= 1;
+    }
+
+    fn finish(&mut self, progress: &Progress, reason: StopReason) {
+        self.write(progress, Some(format!("{:?}", reason)));
+        if let Err(e) = self.out.flush() {
+            eprintln!("couldn't write to log: {}", e);
+        }
+        self.search += 1;
+        self.countdown = 0;
+        self.started = Instant::now();
+    }
+}
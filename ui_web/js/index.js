@@ -97,19 +97,23 @@ async function fetch_song_list() {
     throw "couldn't load song list aaaa";
 }
 
-async function run(step_input, song_sel) {
+async function run(step_input, song_sel, att_sel) {
   insert_throbber();
 
   let song_id = null;
   for(const option of song_sel.selectedOptions)
     song_id = parseInt(option.value);
+  let att_override = undefined;
+  for(const option of att_sel.selectedOptions)
+    if(option.value)
+      att_override = parseInt(option.value);
   const song_json = await fetch_song_text(song_id);
   const step_count = parseInt(step_input.value);
 
   // if(solve_worker)
     // run_worker(card_info, song_id, song_json, step_count);
   // else
-    run_bourgeois(song_id, song_json, step_count);
+    run_bourgeois(song_id, song_json, step_count, att_override);
 }
 /*
 async function run_worker(card_info, song_id, song_json, step_count) {
@@ -125,16 +129,26 @@ async function run_worker(card_info, song_id, song_json, step_count) {
   });
 }
 */
-async function run_bourgeois(song_id, song_json, step_count) {
+async function run_bourgeois(song_id, song_json, step_count, att_override) {
   const account_json = account_as_json();
   let wasm = await(wasm_promise);
-  const output_j = wasm.run_solver(step_count, JSON.stringify(static_card_data), account_json, song_id, song_json, random_u32(), random_u32());
+  const output_j = wasm.run_solver({
+    steps: step_count,
+    cards_json: JSON.stringify(static_card_data),
+    account_json: account_json,
+    song_id: song_id,
+    song_json: song_json,
+    rng_lo: random_u32(),
+    rng_hi: random_u32(),
+    attribute: att_override,
+  });
   const output = JSON.parse(output_j);
-  insert_schedule_display(output);
+  insert_schedule_display(output[0]);
 }
 
 function random_u32() {
-  return (Math.random() * 0x100000000)|0;
+  // >>> 0 rather than |0, which would make half of them negative
+  return (Math.random() * 0x100000000) >>> 0;
 }
 
 async function insert_throbber() {
@@ -357,6 +371,13 @@ async function init_solver() {
   div.appendChild(song_text);
   div.appendChild(song_sel);
   div.appendChild(document.createElement("br"));
+  let att_text = document.createElement("label");
+  att_text.innerText = "Attribute: ";
+  att_text.title = "Pretend the song has a different attribute.";
+  div.appendChild(att_text);
+  let att_sel = make_att_selector();
+  div.appendChild(att_sel);
+  div.appendChild(document.createElement("br"));
   let step_text = document.createElement("label");
   step_text.innerText = "Step count: ";
   step_text.title = "Higher step counts make better teams but take longer to run.";
@@ -366,7 +387,7 @@ async function init_solver() {
   step_input.value = 2000;
   div.appendChild(step_input);
   let btn = document.createElement("button");
-  btn.onclick = function() { run(step_input, song_sel) };
+  btn.onclick = function() { run(step_input, song_sel, att_sel) };
   btn.innerText = "Run";
   btn.id = "run-button";
   btn.disable = false;
@@ -454,6 +475,21 @@ async function make_song_selector(difficulty) {
   return sel;
 }
 
+function make_att_selector() {
+  let sel = document.createElement("select");
+  let option = document.createElement("option");
+  option.value = "";
+  option.innerText = "song default";
+  sel.appendChild(option);
+  for(const att of [1, 2, 3, 4, 5, 6]) {
+    let option = document.createElement("option");
+    option.value = att;
+    option.innerText = attribute_names[att];
+    sel.appendChild(option);
+  }
+  return sel;
+}
+
 function make_diff_selector(song_selector) {
   let sel = document.createElement("select");
   for(let [diff, name] of [["a", "Advanced"], ["i", "Intermediate"], ["b", "Beginner"]]) {
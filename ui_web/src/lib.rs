@@ -1,27 +1,49 @@
 use std::collections::BTreeMap;
 
-use serde::Serialize;
+use serde::{Serialize, Deserialize};
 use wasm_bindgen::prelude::*;
 use web_sys::console;
 use rand::SeedableRng;
-use rand::rngs::SmallRng;
 
+use idolsched::{PlayGlob, ScheduleDisplayInfo};
 use idolsched::schedule::Schedule;
 use idolsched::accessory::Acc;
-use idolsched::card::Card;
-use local_search::{SimpleIterSolver, anneal};
+use idolsched::report::Report;
+use idolsched::team::TeamSpec;
+use local_search::{SimpleIterSolver, PortableRng, anneal};
+use local_search::anneal::CoolingKind;
+use local_search::observe::{self, Observer, Progress};
+use local_search::stop::Stop;
+use local_search::top::TopK;
 use card_consumer::json_card::TrimCard;
+use sifas_data::prelude::Attribute;
 
 #[cfg(feature = "wee_alloc")]
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
+// how to anneal, as with the command line's --cooling, --auto-t0 and --refine
+#[derive(Debug, Copy, Clone, Default)]
+struct Tuning {
+    cooling: CoolingKind,
+    auto_t0: bool,
+    refine: bool,
+}
+
+// one of the teams run_solver found. `attribute` is only there with all_attributes
+#[derive(Serialize)]
+pub struct TeamResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attribute: Option<Attribute>,
+    #[serde(flatten)]
+    schedule: ScheduleDisplayInfo,
+}
+
 #[derive(Serialize)]
-pub struct ScheduleDisplayInfo {
-    voltage: f64,
-    cards: [u32; 9], // ordinals, RRRGGGBBB
-    sp3: [u32; 3], // indexes into `cards` (as u32)
-    accs: [u32; 9], // indexes into the account's info (as u32)
+pub struct Evaluation {
+    #[serde(flatten)]
+    schedule: ScheduleDisplayInfo,
+    report: Report,
 }
 
 #[wasm_bindgen(start)]
@@ -31,37 +53,191 @@ pub fn main_js() -> Result<(), JsValue> {
     Ok(())
 }
 
+// what run_solver can be asked to do, as a js object.
+// the first seven are required; the rest can be left out.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SolverOptions {
+    steps: u32,
+    cards_json: String,
+    account_json: String,
+    song_id: u32,
+    song_json: String,
+    // the seed is rng_hi << 32 | rng_lo; the same seed and options always give the same teams
+    rng_lo: u32,
+    rng_hi: u32,
+    // 0123456 = XSPCANE, as with the command line's --attribute; left out keeps the song's own
+    attribute: Option<u32>,
+    // search once for each main attribute instead, seeded with the seed, the seed + 1, and so on
+    #[serde(default)]
+    all_attributes: bool,
+    // as with the command line's --cooling, --auto-t0 and --refine
+    cooling: Option<String>,
+    #[serde(default)]
+    auto_t0: bool,
+    #[serde(default)]
+    refine: bool,
+    // a team to start from instead of a random one, as json or a team code (see idolsched::team::TeamSpec)
+    start: Option<String>,
+    // up to `top` teams for each search, best first, each with at least `distinct` cards
+    // that none of the teams before it have (see the command line's --top and --distinct)
+    #[serde(default = "SolverOptions::default_top")]
+    top: u32,
+    #[serde(default = "SolverOptions::default_distinct")]
+    distinct: u32,
+    // with a `progress` function in the options, how often to call it
+    #[serde(default = "SolverOptions::default_progress_every")]
+    progress_every: u32,
+}
+
+impl SolverOptions {
+    fn default_top() -> u32 {
+        1
+    }
+
+    fn default_distinct() -> u32 {
+        2
+    }
+
+    fn default_progress_every() -> u32 {
+        1000
+    }
+}
+
+// searches for the best team with `options` (see SolverOptions), and returns a json list of the teams found,
+// each hill climbed. if the options have a `progress` function, it's called every `progress_every` steps
+// (and once at the end) with a json progress report: step, fraction, temperature, current, best,
+// accept_rate and memo. energies are negative voltage.
+// throws if the options are missing something or don't make sense.
+#[wasm_bindgen]
+pub fn run_solver(options: JsValue) -> Result<String, JsValue> {
+    // a function doesn't survive JSON.stringify, so it's taken out first
+    let progress = js_sys::Reflect::get(&options, &JsValue::from_str("progress"))?
+        .dyn_into::<js_sys::Function>()
+        .ok();
+    let text = String::from(js_sys::JSON::stringify(&options)?);
+    let options: SolverOptions = serde_json::from_str(&text)
+        .map_err(|e| JsValue::from_str(&format!("bad solver options: {}", e)))?;
+
+    let cooling = match options.cooling {
+        Some(ref name) => CoolingKind::parse(name)
+            .ok_or_else(|| JsValue::from_str("cooling must be geometric, linear, target, reheat or lam"))?,
+        None => CoolingKind::default(),
+    };
+    let att_override = match options.attribute {
+        Some(digit) => Some(Attribute::from_digit(digit)
+            .ok_or_else(|| JsValue::from_str("attribute must be from 0 to 6"))?),
+        None => None,
+    };
+    if att_override.is_some() && options.all_attributes {
+        return Err(JsValue::from_str("attribute can't be used with all_attributes"));
+    }
+    if options.top == 0 || options.distinct == 0 || options.distinct > 9 {
+        return Err(JsValue::from_str("top must be at least 1, and distinct from 1 to 9"));
+    }
+
+    let glob = make_glob(&options.cards_json, &options.account_json, options.song_id, &options.song_json, att_override);
+    let start = match options.start {
+        Some(ref team_text) => Some(parse_team(&glob, team_text)?),
+        None => None,
+    };
+    let tuning = Tuning { cooling, auto_t0: options.auto_t0, refine: options.refine };
+    let rng_seed = (options.rng_hi as u64) << 32 | options.rng_lo as u64;
+    let globs: Vec<(Option<Attribute>, PlayGlob)> = if options.all_attributes {
+        sifas_data::MAIN_ATTRIBUTES.iter().map(|&attribute| (Some(attribute), glob.with_attribute(attribute))).collect()
+    } else {
+        vec![(None, glob)]
+    };
+
+    let mut results = Vec::new();
+    for (i, (attribute, glob)) in globs.iter().enumerate() {
+        let seed = rng_seed.wrapping_add(i as u64);
+        let mut top = TopK::new(options.top as usize, options.distinct as usize, Schedule::cards_differing);
+        match progress {
+            Some(ref callback) => {
+                let mut observer = JsProgress { callback, every: options.progress_every.max(1) as u64, countdown: 0 };
+                solve(glob, options.steps, seed, start, tuning, &mut observer, &mut top)
+            },
+            None => solve(glob, options.steps, seed, start, tuning, &mut (), &mut top),
+        }
+        for (sched, energy) in top.climbed(glob).entries() {
+            results.push(TeamResult { attribute: *attribute, schedule: ScheduleDisplayInfo::new(glob, sched, *energy) });
+        }
+    }
+    Ok(serde_json::to_string(&results).unwrap())
+}
+
+// scores the team in `team_text` (see idolsched::team::TeamSpec) without searching.
+// throws if the team doesn't make sense for the account.
 #[wasm_bindgen]
-pub fn run_solver(steps: u32, json_cards: &str, acct_json: &str, song_id: u32, song_json: &str, rng_lo: u32, rng_hi: u32)
--> String {
-    let acct = idolsched::init_acct(&acct_json).unwrap();
+pub fn evaluate_team(
+    json_cards: &str, acct_json: &str, song_id: u32, song_json: &str,
+    att_override: Option<u32>, team_text: &str
+) -> Result<String, JsValue> {
+    let att_override = att_override.and_then(Attribute::from_digit);
+    let glob = make_glob(json_cards, acct_json, song_id, song_json, att_override);
+    let sched = parse_team(&glob, team_text)?;
+    let report = Report::from_trace(&glob.trace_voltage(&sched));
+    let schedule = ScheduleDisplayInfo::new(&glob, &sched, -report.voltage);
+
+    Ok(serde_json::to_string(&Evaluation { schedule, report }).unwrap())
+}
+
+fn make_glob(json_cards: &str, acct_json: &str, song_id: u32, song_json: &str, att_override: Option<Attribute>)
+-> PlayGlob {
+    let acct = idolsched::init_acct(acct_json).unwrap();
     let card_details = parse_cards(json_cards);
-    let glob = idolsched::init_glob(&card_details, &acct, song_id, &song_json).unwrap();
-    let rng_seed = (rng_hi as u64) << 32 | rng_lo as u64;
-    let mut rng = SmallRng::seed_from_u64(rng_seed);
-    let s0 = Schedule::new_random(&mut rng, glob.album.len(), glob.inventory.len());
-    let pm = anneal::Params { rng, t0: 10_000.0, alpha: 1.0 - (1.0/65_536.0) };
+    idolsched::init_glob(&card_details, &acct, song_id, song_json, att_override).unwrap()
+}
+
+fn parse_team(glob: &PlayGlob, team_text: &str) -> Result<Schedule, JsValue> {
+    TeamSpec::parse(team_text)
+        .and_then(|team| team.to_schedule(glob))
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+// offers `top` every team the search visits, and the one it ends up with, hill climbed
+fn solve<O: Observer>(
+    glob: &PlayGlob, steps: u32, rng_seed: u64, start: Option<Schedule>, tuning: Tuning,
+    observer: &mut O, top: &mut TopK<Schedule>
+) {
+    let mut rng = PortableRng::seed_from_u64(rng_seed);
+    let s0 = start.unwrap_or_else(|| Schedule::new_random(&mut rng, glob));
+    let t0 = anneal::start_temp(&mut rng, &s0, glob, tuning.auto_t0, tuning.refine);
+    let pm = anneal::Params { rng, t0, cooling: tuning.cooling.cooling(steps as u64, t0) };
     let mut annealer = anneal::Annealer::org(s0, glob.clone(), pm);
-    let (final_sched, energy) = local_search::search_n(&mut annealer, steps).unwrap();
+    let found = observe::search_observed_top(&mut annealer, Stop::steps(steps as u64), observer, top).0.unwrap();
+    // as ui_cli does, so that a seed gives the same team in both
+    let (sched, energy, _) = local_search::hill_climb(found.0, glob);
+    top.offer(&sched, energy);
+}
 
-    serde_json::to_string(&sdi(&glob.album, final_sched, energy)).unwrap()
+struct JsProgress<'a> {
+    callback: &'a js_sys::Function,
+    every: u64,
+    countdown: u64,
 }
 
-fn sdi(album: &[Card], sched: Schedule, energy: f64) -> ScheduleDisplayInfo {
-    let mut cards = [0; 9];
-    for (i, card_i) in sched.cards.iter().enumerate() {
-        cards[i] = album[*card_i].ordinal;
+impl JsProgress<'_> {
+    fn report(&self, progress: &Progress) {
+        let json = JsValue::from_str(&serde_json::to_string(progress).unwrap());
+        // an exception in the callback shouldn't stop the search
+        let _ = self.callback.call1(&JsValue::NULL, &json);
     }
-    let mut sp3 = [0; 3];
-    for (i, v) in sched.sp3.iter().enumerate() {
-        sp3[i] = *v as u32;
+}
+
+impl Observer for JsProgress<'_> {
+    fn observe(&mut self, progress: &Progress) {
+        if self.countdown == 0 {
+            self.report(progress);
+            self.countdown = self.every;
+        }
+        self.countdown -= 1;
     }
-    let mut accs = [0; 9];
-    for (i, v) in sched.accs.iter().enumerate() {
-        accs[i] = *v as u32;
+
+    fn finish(&mut self, progress: &Progress, _reason: local_search::stop::StopReason) {
+        self.report(progress);
     }
-    let voltage = -energy;
-    ScheduleDisplayInfo { voltage, cards, sp3, accs }
 }
 
 fn parse_cards(json_cards: &str) -> BTreeMap<u32, TrimCard> {